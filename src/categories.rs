@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use crate::structs::{Category, Expense};
+
+// ------------------------------------------------------------
+// CATEGORIES
+// ------------------------------------------------------------
+/// Separator between the levels of a category path, e.g. "Housing > Utilities > Electricity".
+pub const PATH_SEPARATOR: &str = " > ";
+
+/// Split a category path typed by the user. Both '>' and '/' separate levels.
+pub fn parse_path(path: &str) -> Vec<String> {
+    return path.split(['>', '/'])
+        .map(|level| level.trim().to_string())
+        .filter(|level| !level.is_empty())
+        .collect();
+}
+
+/// Estimated and spent amounts of a category, including its sub-categories.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Subtotal {
+    pub estimate: i64, // in cents.
+    pub spent: i64, // in cents.
+}
+
+/// Every category, organised as a tree.
+pub struct CategoryTree {
+    categories: Vec<Category>,
+}
+
+impl CategoryTree {
+    pub fn new(categories: Vec<Category>) -> CategoryTree {
+        return CategoryTree { categories };
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Category> {
+        return self.categories.iter().find(|c| c.id == id);
+    }
+
+    /// The category itself, then its parent, up to the top-level category.
+    pub fn ancestors(&self, id: u32) -> Vec<&Category> {
+        let mut res = Vec::new();
+        let mut current = self.get(id);
+
+        while let Some(category) = current {
+            res.push(category);
+            current = category.parent_id.and_then(|p| self.get(p));
+        }
+
+        return res;
+    }
+
+    /// Depth of a category, top-level categories being at depth 1.
+    pub fn depth(&self, id: u32) -> usize {
+        return self.ancestors(id).len();
+    }
+
+    /// Full path of a category, e.g. "Housing > Utilities > Electricity".
+    pub fn path(&self, id: u32) -> String {
+        let names: Vec<&str> = self.ancestors(id).iter().rev().map(|c| c.name.as_str()).collect();
+        return names.join(PATH_SEPARATOR);
+    }
+
+    /// Every category in depth-first order, sorted by name among siblings, with its depth.
+    pub fn walk(&self) -> Vec<(&Category, usize)> {
+        let mut res = Vec::new();
+        self.walk_from(None, 1, &mut res);
+        return res;
+    }
+
+    fn walk_from<'a>(&'a self, parent: Option<u32>, depth: usize, res: &mut Vec<(&'a Category, usize)>) {
+        let mut children: Vec<&Category> = self.categories.iter()
+            .filter(|c| c.parent_id == parent)
+            .collect();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for child in children {
+            res.push((child, depth));
+            self.walk_from(Some(child.id), depth + 1, res);
+        }
+    }
+
+    /// Subtotals of every category holding expense lines, directly or through its sub-categories.
+    pub fn subtotals(&self, expenses: &[Expense]) -> HashMap<u32, Subtotal> {
+        let mut res: HashMap<u32, Subtotal> = HashMap::new();
+
+        for expense in expenses {
+            let Some(category_id) = expense.category_id else { continue };
+
+            for category in self.ancestors(category_id) {
+                let subtotal = res.entry(category.id).or_default();
+                subtotal.estimate += expense.estimate;
+                subtotal.spent += expense.spent;
+            }
+        }
+
+        return res;
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use crate::error::{Error, Result};
+use crate::structs::{ExchangeRate, Expense, Income};
+
+// ------------------------------------------------------------
+// CURRENCIES
+// ------------------------------------------------------------
+/// Currency the exchange rates are given against, as in the ECB reference rates.
+pub const BASE_CURRENCY: &str = "EUR";
+
+/// Decimal precision used when a currency is not listed below.
+const DEFAULT_PRECISION: u32 = 2;
+
+/// ISO 4217 code, symbol and number of decimals of the usual currencies.
+/// Currencies sharing a symbol are written with their code.
+const CURRENCIES: &[(&str, &str, u32)] = &[
+    ("EUR", "€", 2),
+    ("USD", "$", 2),
+    ("GBP", "£", 2),
+    ("JPY", "¥", 0),
+    ("CHF", "Fr", 2),
+    ("INR", "₹", 2),
+    ("KRW", "₩", 0),
+    ("TRY", "₺", 2),
+    ("ILS", "₪", 2),
+    ("PLN", "zł", 2),
+    ("CAD", "CA$", 2),
+    ("AUD", "A$", 2),
+    ("NZD", "NZ$", 2),
+    ("HKD", "HK$", 2),
+    ("SGD", "S$", 2),
+    ("CNY", "CN¥", 2),
+    ("BRL", "R$", 2),
+    ("MXN", "MX$", 2),
+    ("SEK", "SEK", 2),
+    ("NOK", "NOK", 2),
+    ("DKK", "DKK", 2),
+    ("CZK", "Kč", 2),
+    ("HUF", "Ft", 2),
+    ("RON", "lei", 2),
+    ("BGN", "BGN", 2),
+    ("ISK", "ISK", 0),
+    ("ZAR", "ZAR", 2),
+    ("THB", "฿", 2),
+    ("IDR", "Rp", 2),
+    ("PHP", "₱", 2),
+    ("MYR", "RM", 2),
+    ("VND", "₫", 0),
+    ("CLP", "CLP", 0),
+    ("XOF", "XOF", 0),
+    ("XAF", "XAF", 0),
+    ("KWD", "KWD", 3),
+    ("BHD", "BHD", 3),
+    ("OMR", "OMR", 3),
+    ("JOD", "JOD", 3),
+    ("TND", "TND", 3),
+];
+
+/// Number of decimals of a currency, e.g. 0 for JPY and 3 for KWD.
+pub fn precision(code: &str) -> u32 {
+    return CURRENCIES.iter()
+        .find(|(c, _, _)| *c == code)
+        .map_or(DEFAULT_PRECISION, |(_, _, p)| *p);
+}
+
+/// Symbol of a currency, or its code if it has no symbol of its own.
+pub fn symbol(code: &str) -> &str {
+    return CURRENCIES.iter()
+        .find(|(c, _, _)| *c == code)
+        .map_or(code, |(_, s, _)| *s);
+}
+
+/// ISO 4217 code of a currency, from its symbol or the code itself.
+pub fn code_of(currency: &str) -> Option<String> {
+    let currency = currency.trim();
+
+    if let Some((code, _, _)) = CURRENCIES.iter().find(|(_, s, _)| *s == currency) {
+        return Some(code.to_string());
+    }
+
+    let valid = currency.len() == 3 && currency.chars().all(|c| c.is_ascii_alphabetic());
+    return if valid { Some(currency.to_ascii_uppercase()) } else { None };
+}
+
+/// Same as `code_of`, but a currency without a code is an error.
+pub fn parse_code(currency: &str) -> Result<String> {
+    return code_of(currency).ok_or_else(|| Error::UnknownCurrency(currency.to_string()));
+}
+
+/// Exchange rates by currency, in units of the currency for one unit of `BASE_CURRENCY`.
+#[derive(Debug, Default)]
+pub struct Rates {
+    /// Rates of each currency, sorted by date.
+    rates: HashMap<String, Vec<(NaiveDate, f64)>>,
+}
+
+impl Rates {
+    pub fn new(rates: Vec<ExchangeRate>) -> Rates {
+        let mut res = Rates::default();
+
+        for rate in rates {
+            res.rates.entry(rate.currency).or_default().push((rate.date, rate.rate));
+        }
+
+        for history in res.rates.values_mut() {
+            history.sort_by_key(|(date, _)| *date);
+        }
+
+        return res;
+    }
+
+    /// The latest rate of a currency known on a date.
+    pub fn rate(&self, currency: &str, date: NaiveDate) -> Result<f64> {
+        if currency == BASE_CURRENCY {
+            return Ok(1.0);
+        }
+
+        return self.rates.get(currency)
+            .and_then(|history| history.iter().rev().find(|(d, _)| *d <= date))
+            .map(|(_, rate)| *rate)
+            .ok_or_else(|| Error::MissingRate(currency.to_string(), date));
+    }
+
+    /// Convert an amount, in the smallest unit of its currency, at the rates known on a date.
+    pub fn convert(&self, amount: i64, from: &str, to: &str, date: NaiveDate) -> Result<i64> {
+        if from == to || amount == 0 {
+            return Ok(amount);
+        }
+
+        let units = amount as f64 / 10f64.powi(precision(from) as i32);
+        let converted = units / self.rate(from, date)? * self.rate(to, date)?;
+
+        return Ok((converted * 10f64.powi(precision(to) as i32)).round() as i64);
+    }
+
+    /// Incomes with their amounts converted into another currency.
+    pub fn convert_incomes(&self, incomes: &[Income], to: &str, date: NaiveDate) -> Result<Vec<Income>> {
+        return incomes.iter()
+            .map(|income| {
+                Ok(Income {
+                    value: self.convert(income.value, &income.currency, to, date)?,
+                    received: self.convert(income.received, &income.currency, to, date)?,
+                    currency: to.to_string(),
+                    ..income.clone()
+                })
+            })
+            .collect();
+    }
+
+    /// Expense lines with their amounts converted into another currency.
+    pub fn convert_expenses(&self, expenses: &[Expense], to: &str, date: NaiveDate) -> Result<Vec<Expense>> {
+        return expenses.iter()
+            .map(|expense| {
+                Ok(Expense {
+                    estimate: self.convert(expense.estimate, &expense.currency, to, date)?,
+                    spent: self.convert(expense.spent, &expense.currency, to, date)?,
+                    refunded: self.convert(expense.refunded, &expense.currency, to, date)?,
+                    currency: to.to_string(),
+                    ..expense.clone()
+                })
+            })
+            .collect();
+    }
+}
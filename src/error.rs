@@ -0,0 +1,118 @@
+use std::fmt;
+use chrono::NaiveDate;
+use crate::migrations::MigrationError;
+use crate::structs::Period;
+use crate::utils::AmountError;
+
+// ------------------------------------------------------------
+// ERRORS
+// ------------------------------------------------------------
+#[derive(Debug)]
+pub enum Error {
+    Sql(rusqlite::Error),
+    Migration(MigrationError),
+    InvalidAmount(String, AmountError),
+    UnknownExpense(String),
+    UnknownIncome(String),
+    IncomeExists(String),
+    UnknownTransaction(u32),
+    InvalidCategory(String),
+    UnknownCurrency(String),
+    MissingRate(String, NaiveDate),
+    CurrencyMismatch(String, String),
+    UnknownPeriod(String),
+    DuplicatePeriodName(String),
+    InvalidPeriodDates(NaiveDate, NaiveDate),
+    PeriodNotEmpty(u32),
+    FutureOngoingPeriod(NaiveDate),
+    PeriodStartedToday(u32),
+    DateOutsidePeriod(NaiveDate, Period),
+    OverlappingPeriod(NaiveDate, Option<NaiveDate>, Period),
+    HistoryConflict(u32),
+    Json(serde_json::Error),
+    Yaml(serde_yaml::Error),
+    Io(std::io::Error),
+    Config(String),
+    Import(String),
+    Export(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Sql(e) => write!(f, "Database error : {}", e),
+            Error::Migration(e) => write!(f, "{}", e),
+            Error::InvalidAmount(value, reason) => write!(f, "'{}' is not a valid amount : {}", value, reason),
+            Error::UnknownExpense(label) => write!(f, "No expense line labelled {}", label),
+            Error::UnknownIncome(label) => write!(f, "No income labelled {}", label),
+            Error::IncomeExists(label) => write!(f, "Income {} already exists : --expected, --on and --recurring only apply when it is created, use `recurring` to change its recurrence", label),
+            Error::UnknownTransaction(id) => write!(f, "No transaction #{}", id),
+            Error::InvalidCategory(path) => write!(f, "Invalid category path : '{}'", path),
+            Error::UnknownCurrency(currency) => write!(f, "Unknown currency '{}', expected an ISO 4217 code such as EUR", currency),
+            Error::MissingRate(currency, date) => write!(f, "No exchange rate for {} on or before {}, import some with `ebenezer import rates`", currency, date),
+            Error::CurrencyMismatch(label, currency) => write!(f, "{} is kept in {}, give its amounts in that currency", label, currency),
+            Error::UnknownPeriod(period) => write!(f, "No period {}", period),
+            Error::DuplicatePeriodName(name) => write!(f, "A period is already named {}", name),
+            Error::InvalidPeriodDates(start, end) => write!(f, "A period cannot end on {} before it starts on {}", end, start),
+            Error::PeriodNotEmpty(id) => write!(f, "Period {} holds transactions or received incomes, it cannot be deleted", id),
+            Error::FutureOngoingPeriod(start) => write!(f, "An ongoing period cannot start in the future ({}), give it an end date", start),
+            Error::PeriodStartedToday(id) => write!(f, "Period {} started today, it can only be rolled from tomorrow on", id),
+            Error::DateOutsidePeriod(date, period) => {
+                write!(f, "{} is outside of period {} ({} -> {})",
+                    date,
+                    period.id,
+                    period.start_date,
+                    period.end_date.map(|d| d.to_string()).unwrap_or("today".to_string()))
+            },
+            Error::OverlappingPeriod(start, end, period) => {
+                write!(f, "{} -> {} overlaps period {} ({} -> {})",
+                    start,
+                    end.map(|d| d.to_string()).unwrap_or("ongoing".to_string()),
+                    period.id,
+                    period.start_date,
+                    period.end_date.map(|d| d.to_string()).unwrap_or("ongoing".to_string()))
+            },
+            Error::HistoryConflict(id) => write!(f, "The rows changed by log #{} have been modified since, it cannot be undone or redone", id),
+            Error::Json(e) => write!(f, "Invalid JSON : {}", e),
+            Error::Yaml(e) => write!(f, "Invalid YAML : {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Config(message) => write!(f, "Invalid configuration, {}", message),
+            Error::Import(message) => write!(f, "Import failed, {}", message),
+            Error::Export(message) => write!(f, "Export failed, {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Sql(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::Yaml(e)
+    }
+}
+
+impl From<MigrationError> for Error {
+    fn from(e: MigrationError) -> Self {
+        Error::Migration(e)
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use crate::error::Result;
+use crate::export::{currencies, PeriodData};
+use crate::structs::{Expense, Income};
+use crate::utils::print_decimal;
+
+// ------------------------------------------------------------
+// BEANCOUNT EXPORT
+// ------------------------------------------------------------
+/// Account money is received into and spent from.
+pub const ASSETS_ACCOUNT: &str = "Assets:Budget";
+
+/// Account of an expense line, e.g. "Expenses:Fixed:Rent".
+pub fn expense_account(expense: &Expense) -> String {
+    return format!("Expenses:{}:{}", account_name(&expense.expense_type.to_string()), account_name(&expense.label));
+}
+
+/// Account of an income, e.g. "Income:Salary".
+pub fn income_account(income: &Income) -> String {
+    return format!("Income:{}", account_name(&income.label));
+}
+
+/// Write the received incomes and the spending transactions of some periods as a Beancount ledger.
+/// Every account is opened on the start of the earliest period. Incomes are dated on their expected date,
+/// or the start of their period. Transactions are written in the currency they were made in,
+/// `operating_currency` being the reporting one.
+pub fn write_beancount<W: Write>(mut writer: W, periods: &[PeriodData], operating_currency: &str) -> Result<()> {
+    let commodities: Vec<&str> = currencies(periods).into_iter().collect();
+    let mut accounts = BTreeSet::from([ASSETS_ACCOUNT.to_string()]);
+
+    for data in periods {
+        accounts.extend(data.incomes.iter().map(income_account));
+        accounts.extend(data.expenses.iter().map(expense_account));
+    }
+
+    // Accounts must be open before their first entry, whatever its date.
+    let opening = periods.iter()
+        .flat_map(|data| {
+            data.incomes.iter().filter_map(|i| i.expected_date)
+                .chain(data.transactions.iter().map(|t| t.date))
+                .chain([data.period.start_date])
+        })
+        .min();
+
+    writeln!(writer, "; Exported from Ebenezer")?;
+    writeln!(writer, "option \"operating_currency\" \"{}\"", operating_currency)?;
+
+    if let Some(opening) = opening {
+        writeln!(writer)?;
+
+        for account in &accounts {
+            writeln!(writer, "{} open {} {}", opening, account, commodities.join(","))?;
+        }
+    }
+
+    for data in periods {
+        let period = &data.period;
+
+        writeln!(writer)?;
+        writeln!(writer, "; Period {} : {} -> {}",
+            period.id,
+            period.start_date,
+            period.end_date.map(|d| d.to_string()).unwrap_or("ongoing".to_string()))?;
+
+        for income in data.incomes.iter().filter(|i| i.received != 0) {
+            writeln!(writer)?;
+            writeln!(writer, "{} * {}", income.expected_date.unwrap_or(period.start_date), quote(&income.label))?;
+            writeln!(writer, "  {}  {} {}", ASSETS_ACCOUNT, print_decimal(income.received, &income.currency), income.currency)?;
+            writeln!(writer, "  {}", income_account(income))?;
+        }
+
+        for transaction in &data.transactions {
+            let Some(expense) = data.expense_of(transaction) else { continue };
+
+            writeln!(writer)?;
+
+            // With a memo, the label is the payee and the memo the narration.
+            match &transaction.memo {
+                Some(memo) => writeln!(writer, "{} * {} {}", transaction.date, quote(&expense.label), quote(memo))?,
+                None => writeln!(writer, "{} * {}", transaction.date, quote(&expense.label))?,
+            }
+
+            writeln!(writer, "  {}  {} {}",
+                expense_account(expense),
+                print_decimal(transaction.amount, &transaction.currency),
+                transaction.currency)?;
+            writeln!(writer, "  {}", ASSETS_ACCOUNT)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A label as an account name component : capitalized words of letters and digits, joined by '-'.
+fn account_name(label: &str) -> String {
+    let words: Vec<String> = label.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+            format!("{}{}", first, chars.as_str().to_lowercase())
+        })
+        .collect();
+
+    return if words.is_empty() { "Unnamed".to_string() } else { words.join("-") };
+}
+
+fn quote(text: &str) -> String {
+    return format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " "));
+}
@@ -0,0 +1,42 @@
+use std::io::{self, Write};
+use ::csv::Writer;
+use crate::error::Result;
+use crate::export::PeriodData;
+use crate::utils::print_decimal;
+
+// ------------------------------------------------------------
+// CSV EXPORT
+// ------------------------------------------------------------
+const HEADERS: [&str; 9] = ["period_id", "start_date", "end_date", "kind", "label", "type", "estimate", "spent", "currency"];
+
+/// Write the incomes and expense lines of some periods, one per row, with plain decimal amounts.
+/// For incomes, the estimate is the expected amount and the spent column what was received.
+/// Ongoing periods have an empty end date, incomes an empty type. Amounts are in the currency of their line.
+pub fn write_periods<W: Write>(writer: W, periods: &[PeriodData]) -> Result<()> {
+    let mut writer = Writer::from_writer(writer);
+    writer.write_record(HEADERS).map_err(io::Error::from)?;
+
+    for data in periods {
+        let period = &data.period;
+        let id = period.id.to_string();
+        let start_date = period.start_date.to_string();
+        let end_date = period.end_date.map(|d| d.to_string()).unwrap_or_default();
+
+        for income in &data.incomes {
+            writer.write_record([
+                &id, &start_date, &end_date, "income", &income.label, "",
+                &print_decimal(income.value, &income.currency), &print_decimal(income.received, &income.currency), &income.currency,
+            ]).map_err(io::Error::from)?;
+        }
+
+        for expense in &data.expenses {
+            writer.write_record([
+                &id, &start_date, &end_date, "expense", &expense.label, &expense.expense_type.to_string(),
+                &print_decimal(expense.estimate, &expense.currency), &print_decimal(expense.spent, &expense.currency), &expense.currency,
+            ]).map_err(io::Error::from)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use crate::error::Result;
+use crate::currencies::precision;
+use crate::export::{currencies, PeriodData};
+use crate::settings::currency_symbol;
+use crate::structs::{Expense, Income};
+use crate::utils::print_decimal;
+
+// ------------------------------------------------------------
+// LEDGER / HLEDGER JOURNAL EXPORT
+// ------------------------------------------------------------
+/// Account money is received into and spent from.
+pub const ASSETS_ACCOUNT: &str = "assets:budget";
+
+/// Account of an expense line, e.g. "expenses:fixed:rent".
+pub fn expense_account(expense: &Expense) -> String {
+    return format!("expenses:{}:{}", expense.expense_type.to_string().to_lowercase(), account_name(&expense.label));
+}
+
+/// Account of an income, e.g. "income:salary".
+pub fn income_account(income: &Income) -> String {
+    return format!("income:{}", account_name(&income.label));
+}
+
+/// Write the received incomes and the spending transactions of some periods as a ledger journal,
+/// readable by ledger and hledger. Incomes are dated on their expected date, or the start of their period.
+/// Accounts and commodities are declared, so that `hledger check --strict` passes.
+/// Transactions are written in the currency they were made in.
+pub fn write_journal<W: Write>(mut writer: W, periods: &[PeriodData]) -> Result<()> {
+    let mut accounts = BTreeSet::from([ASSETS_ACCOUNT.to_string()]);
+
+    for data in periods {
+        accounts.extend(data.incomes.iter().map(income_account));
+        accounts.extend(data.expenses.iter().map(expense_account));
+    }
+
+    writeln!(writer, "; Exported from Ebenezer")?;
+    // Declaring the commodity with a sample amount sets '.' as the decimal mark, and its precision.
+    for currency in currencies(periods) {
+        let sample = 1000 * 10i64.pow(precision(currency));
+        writeln!(writer, "commodity {} {}", print_decimal(sample, currency), commodity_symbol(currency))?;
+    }
+
+    writeln!(writer)?;
+
+    for account in &accounts {
+        writeln!(writer, "account {}", account)?;
+    }
+
+    for data in periods {
+        let period = &data.period;
+
+        writeln!(writer)?;
+        writeln!(writer, "; Period {} : {} -> {}",
+            period.id,
+            period.start_date,
+            period.end_date.map(|d| d.to_string()).unwrap_or("ongoing".to_string()))?;
+
+        for income in data.incomes.iter().filter(|i| i.received != 0) {
+            writeln!(writer)?;
+            writeln!(writer, "{} {}", income.expected_date.unwrap_or(period.start_date), description(&income.label))?;
+            writeln!(writer, "    {}  {} {}", ASSETS_ACCOUNT, print_decimal(income.received, &income.currency), commodity_symbol(&income.currency))?;
+            writeln!(writer, "    {}", income_account(income))?;
+        }
+
+        for transaction in &data.transactions {
+            let Some(expense) = data.expense_of(transaction) else { continue };
+
+            writeln!(writer)?;
+            write!(writer, "{} {}", transaction.date, description(&expense.label))?;
+
+            match &transaction.memo {
+                Some(memo) => writeln!(writer, "  ; {}", memo.replace('\n', " "))?,
+                None => writeln!(writer)?,
+            }
+
+            writeln!(writer, "    {}  {} {}",
+                expense_account(expense),
+                print_decimal(transaction.amount, &transaction.currency),
+                commodity_symbol(&transaction.currency))?;
+            writeln!(writer, "    {}", ASSETS_ACCOUNT)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A label as an account name component : lowercase, single spaces, without ':' or ';'.
+fn account_name(label: &str) -> String {
+    let cleaned = label.to_lowercase().replace([':', ';'], " ");
+    return cleaned.split_whitespace().collect::<Vec<&str>>().join(" ");
+}
+
+/// A label as a transaction description, which ends at the first ';'.
+fn description(label: &str) -> String {
+    return label.replace(';', ",");
+}
+
+/// Commodity of a currency : its symbol, quoted if it holds digits, spaces or signs.
+fn commodity_symbol(code: &str) -> String {
+    let currency = currency_symbol(code);
+    let plain = currency.chars().all(|c| !c.is_ascii_digit() && !c.is_whitespace() && !"-+.,@*;\"{}=()[]".contains(c));
+    return if plain && !currency.is_empty() { currency } else { format!("\"{}\"", currency) };
+}
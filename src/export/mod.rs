@@ -0,0 +1,36 @@
+use std::collections::BTreeSet;
+use crate::structs::{Expense, Income, Period, Transaction};
+
+pub mod beancount;
+pub mod csv;
+pub mod journal;
+
+// ------------------------------------------------------------
+// EXPORT
+// ------------------------------------------------------------
+/// A period along with its incomes, expense lines and their transactions.
+#[derive(Debug)]
+pub struct PeriodData {
+    pub period: Period,
+    pub incomes: Vec<Income>,
+    pub expenses: Vec<Expense>,
+    /// Transactions of every expense line, by date.
+    pub transactions: Vec<Transaction>,
+}
+
+impl PeriodData {
+    /// The expense line a transaction belongs to.
+    pub fn expense_of(&self, transaction: &Transaction) -> Option<&Expense> {
+        return self.expenses.iter().find(|e| e.id == transaction.expense_id);
+    }
+}
+
+/// Codes of every currency the incomes and transactions of some periods are in.
+pub fn currencies(periods: &[PeriodData]) -> BTreeSet<&str> {
+    return periods.iter()
+        .flat_map(|data| {
+            data.incomes.iter().map(|i| i.currency.as_str())
+                .chain(data.transactions.iter().map(|t| t.currency.as_str()))
+        })
+        .collect();
+}
@@ -0,0 +1,188 @@
+use rusqlite::Connection;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::Result;
+
+// ------------------------------------------------------------
+// HISTORY
+// ------------------------------------------------------------
+/// Column values of a database row, by column name.
+pub type Row = Map<String, Value>;
+
+/// State of one row before and after a command. `None` means the row did not exist.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Change {
+    pub table: String,
+    pub id: i64,
+    pub before: Option<Row>,
+    pub after: Option<Row>,
+}
+
+/// Every row touched by a command, in the order they were touched.
+/// Stored along with the log line, so the command can be undone and redone.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Changeset {
+    pub changes: Vec<Change>,
+}
+
+impl Changeset {
+    /// Remember the state of an existing row before it gets updated or deleted.
+    pub fn track(&mut self, conn: &Connection, table: &str, id: i64) -> Result<()> {
+        if !self.is_tracked(table, id) {
+            let before = snapshot(conn, table, id)?;
+            self.changes.push(Change { table: table.to_string(), id, before, after: None });
+        }
+
+        Ok(())
+    }
+
+    /// Remember a row inserted by the command.
+    pub fn inserted(&mut self, table: &str, id: i64) {
+        if !self.is_tracked(table, id) {
+            self.changes.push(Change { table: table.to_string(), id, before: None, after: None });
+        }
+    }
+
+    /// Capture the final state of every tracked row, once the command is done.
+    pub fn seal(&mut self, conn: &Connection) -> Result<()> {
+        for change in self.changes.iter_mut() {
+            change.after = snapshot(conn, &change.table, change.id)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.changes.is_empty();
+    }
+
+    /// Whether the tracked rows are still in the state the command left them in.
+    pub fn is_applied(&self, conn: &Connection) -> Result<bool> {
+        for change in &self.changes {
+            if !matches(conn, &change.table, change.id, change.after.as_ref())? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Whether the tracked rows are back in the state they were in before the command.
+    pub fn is_reverted(&self, conn: &Connection) -> Result<bool> {
+        for change in &self.changes {
+            if !matches(conn, &change.table, change.id, change.before.as_ref())? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Put every tracked row back in its state from before the command.
+    pub fn revert(&self, conn: &Connection) -> Result<()> {
+        // Rows are restored one at a time, so a child row may briefly lack its parent.
+        conn.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+
+        for change in self.changes.iter().rev() {
+            restore(conn, &change.table, change.id, change.before.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    /// Put every tracked row back in its state from after the command.
+    pub fn apply(&self, conn: &Connection) -> Result<()> {
+        conn.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+
+        for change in &self.changes {
+            restore(conn, &change.table, change.id, change.after.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    fn is_tracked(&self, table: &str, id: i64) -> bool {
+        return self.changes.iter().any(|c| c.table == table && c.id == id);
+    }
+}
+
+/// Read a row as a map of column values.
+fn snapshot(conn: &Connection, table: &str, id: i64) -> Result<Option<Row>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE id = ?", table))?;
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+    let mut rows = stmt.query([id])?;
+
+    let row = match rows.next()? {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    let mut res = Row::new();
+
+    for (index, name) in names.into_iter().enumerate() {
+        let value = match row.get_ref(index)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(i) => Value::from(i),
+            ValueRef::Real(r) => Value::from(r),
+            ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).to_string()),
+            ValueRef::Blob(_) => Value::Null,
+        };
+
+        res.insert(name, value);
+    }
+
+    return Ok(Some(res));
+}
+
+/// Whether a row is in the given state. Columns added after the state was captured are ignored.
+fn matches(conn: &Connection, table: &str, id: i64, state: Option<&Row>) -> Result<bool> {
+    return Ok(match (snapshot(conn, table, id)?, state) {
+        (None, None) => true,
+        (Some(current), Some(expected)) => expected.iter().all(|(k, v)| current.get(k) == Some(v)),
+        _ => false,
+    });
+}
+
+/// Bring a row to the given state : delete it, update it or insert it back.
+fn restore(conn: &Connection, table: &str, id: i64, state: Option<&Row>) -> Result<()> {
+    let state = match state {
+        Some(s) => s,
+        None => {
+            conn.execute(&format!("DELETE FROM {} WHERE id = ?", table), [id])?;
+            return Ok(());
+        }
+    };
+
+    let columns: Vec<&String> = state.keys().filter(|k| k.as_str() != "id").collect();
+    let mut values: Vec<SqlValue> = columns.iter().map(|c| to_sql_value(&state[c.as_str()])).collect();
+    values.push(SqlValue::Integer(id));
+
+    let exists = snapshot(conn, table, id)?.is_some();
+
+    let sql = if exists {
+        let assignments: Vec<String> = columns.iter().map(|c| format!("{} = ?", c)).collect();
+        format!("UPDATE {} SET {} WHERE id = ?", table, assignments.join(", "))
+    }
+    else {
+        let names: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+        let placeholders = vec!["?"; columns.len() + 1].join(", ");
+        format!("INSERT INTO {} ({}, id) VALUES ({})", table, names.join(", "), placeholders)
+    };
+
+    conn.execute(&sql, rusqlite::params_from_iter(values))?;
+    Ok(())
+}
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    return match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        _ => SqlValue::Text(value.to_string()),
+    };
+}
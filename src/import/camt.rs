@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use crate::error::{Error, Result};
+use crate::import::{occurrence_references, parse_statement_amount, StatementEntry};
+
+// ------------------------------------------------------------
+// ISO 20022 CAMT.053 STATEMENTS
+// ------------------------------------------------------------
+/// Read the booked entries of a camt.053 statement. Pending and informative entries are left out.
+/// Entries are referenced by their AcctSvcrRef, or NtryRef, scoped by account.
+pub fn read_statement<P: AsRef<Path>>(path: P) -> Result<Vec<StatementEntry>> {
+    let content = fs::read_to_string(path)?;
+    let document = Document::parse(&content)
+        .map_err(|e| Error::Import(format!("invalid camt.053 file, {}", e)))?;
+
+    let mut rows = Vec::new();
+
+    for statement in document.descendants().filter(|n| is(n, "Stmt")) {
+        let account = child(statement, &["Acct", "Id", "IBAN"])
+            .or_else(|| child(statement, &["Acct", "Id", "Othr", "Id"]))
+            .unwrap_or_default();
+
+        for entry in statement.children().filter(|n| is(n, "Ntry")) {
+            // camt.053.001.02 holds the status as text, later versions in a <Cd> element.
+            let status = child(entry, &["Sts", "Cd"]).or_else(|| child(entry, &["Sts"])).unwrap_or_default();
+
+            if status != "BOOK" {
+                continue;
+            }
+
+            rows.push(to_row(entry, &account)?);
+        }
+    }
+
+    let keys = rows.iter().map(|(key, _, _, _)| key.clone()).collect();
+
+    let entries = rows.into_iter()
+        .zip(occurrence_references(keys))
+        .map(|((_, date, amount, description), reference)| StatementEntry { reference, date, amount, description })
+        .collect();
+
+    return Ok(entries);
+}
+
+fn to_row(entry: Node, account: &str) -> Result<(String, NaiveDate, i64, String)> {
+    let raw_date = child(entry, &["BookgDt", "Dt"])
+        .or_else(|| child(entry, &["BookgDt", "DtTm"]))
+        .unwrap_or_default();
+
+    let date = raw_date.get(0..10)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .ok_or_else(|| Error::Import(format!("entry without a valid booking date '{}'", raw_date)))?;
+
+    let raw_amount = child(entry, &["Amt"]).unwrap_or_default();
+    let amount = parse_statement_amount(&raw_amount, '.')
+        .ok_or_else(|| Error::Import(format!("entry of {} : invalid amount '{}'", date, raw_amount)))?;
+
+    let amount = match child(entry, &["CdtDbtInd"]).as_deref() {
+        Some("CRDT") => amount,
+        Some("DBIT") => -amount,
+        other => return Err(Error::Import(format!("entry of {} : invalid credit/debit indicator {:?}", date, other))),
+    };
+
+    // The counterparty reads better than the remittance information, when there is one.
+    let counterparty = if amount < 0 { "Cdtr" } else { "Dbtr" };
+
+    let description = child(entry, &["NtryDtls", "TxDtls", "RltdPties", counterparty, "Nm"])
+        .or_else(|| child(entry, &["NtryDtls", "TxDtls", "RltdPties", counterparty, "Pty", "Nm"]))
+        .or_else(|| child(entry, &["NtryDtls", "TxDtls", "RmtInf", "Ustrd"]))
+        .or_else(|| child(entry, &["AddtlNtryInf"]))
+        .unwrap_or_default();
+
+    let key = match child(entry, &["AcctSvcrRef"]).or_else(|| child(entry, &["NtryRef"])) {
+        Some(reference) => format!("camt:{}:{}", account, reference),
+        None => format!("camt:{}:{}|{}|{}", account, date, amount, description),
+    };
+
+    return Ok((key, date, amount, description));
+}
+
+/// Whether a node is the element with the given name, whatever its namespace.
+fn is(node: &Node, name: &str) -> bool {
+    return node.is_element() && node.tag_name().name() == name;
+}
+
+/// Trimmed text of the first descendant found along a path of element names.
+fn child(node: Node, path: &[&str]) -> Option<String> {
+    let mut current = node;
+
+    for name in path {
+        current = current.children().find(|n| is(n, name))?;
+    }
+
+    let text = current.text()?.trim().to_string();
+    return if text.is_empty() { None } else { Some(text) };
+}
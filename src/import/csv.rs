@@ -0,0 +1,111 @@
+use std::path::Path;
+use chrono::NaiveDate;
+use ::csv::{ReaderBuilder, StringRecord};
+use crate::error::{Error, Result};
+use crate::import::{occurrence_references, parse_statement_amount, StatementEntry};
+
+// ------------------------------------------------------------
+// CSV STATEMENTS
+// ------------------------------------------------------------
+/// Where to find each field in a CSV statement, and how to read it.
+/// Columns are given either by header name or by 1-based position.
+#[derive(Debug)]
+pub struct CsvMapping {
+    pub date_column: String,
+    pub amount_column: String,
+    pub description_column: String,
+    /// chrono format of the dates, e.g. "%d/%m/%Y".
+    pub date_format: String,
+    pub decimal_separator: char,
+    pub delimiter: u8,
+    pub has_headers: bool,
+    /// Amounts are positive for money going out.
+    pub inverted: bool,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        return CsvMapping {
+            date_column: "date".to_string(),
+            amount_column: "amount".to_string(),
+            description_column: "description".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            decimal_separator: ',',
+            delimiter: b';',
+            has_headers: true,
+            inverted: false,
+        };
+    }
+}
+
+/// Read the entries of a CSV bank statement.
+pub fn read_statement<P: AsRef<Path>>(path: P, mapping: &CsvMapping) -> Result<Vec<StatementEntry>> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(mapping.delimiter)
+        .has_headers(mapping.has_headers)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| Error::Import(e.to_string()))?;
+
+    let headers = match mapping.has_headers {
+        true => Some(reader.headers().map_err(|e| Error::Import(e.to_string()))?.clone()),
+        false => None,
+    };
+
+    let date_index = column_index(&mapping.date_column, headers.as_ref())?;
+    let amount_index = column_index(&mapping.amount_column, headers.as_ref())?;
+    let description_index = column_index(&mapping.description_column, headers.as_ref())?;
+
+    let mut rows = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| Error::Import(e.to_string()))?;
+        let line = index + if mapping.has_headers { 2 } else { 1 };
+
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+
+        let raw_date = field(&record, date_index, line)?;
+        let date = NaiveDate::parse_from_str(raw_date, &mapping.date_format)
+            .map_err(|_| Error::Import(format!("line {} : '{}' does not match the date format {}", line, raw_date, mapping.date_format)))?;
+
+        let raw_amount = field(&record, amount_index, line)?;
+        let amount = parse_statement_amount(raw_amount, mapping.decimal_separator)
+            .ok_or_else(|| Error::Import(format!("line {} : '{}' is not a valid amount", line, raw_amount)))?;
+
+        let description = field(&record, description_index, line)?.to_string();
+
+        rows.push((date, if mapping.inverted { -amount } else { amount }, description));
+    }
+
+    let keys = rows.iter()
+        .map(|(date, amount, description)| format!("csv:{}|{}|{}", date, amount, description))
+        .collect();
+
+    let entries = rows.into_iter()
+        .zip(occurrence_references(keys))
+        .map(|((date, amount, description), reference)| StatementEntry { reference, date, amount, description })
+        .collect();
+
+    return Ok(entries);
+}
+
+/// Resolve a column given by header name or by 1-based position.
+fn column_index(column: &str, headers: Option<&StringRecord>) -> Result<usize> {
+    if let Ok(position) = column.parse::<usize>() {
+        if position > 0 {
+            return Ok(position - 1);
+        }
+    }
+
+    return headers
+        .and_then(|h| h.iter().position(|name| name.trim().eq_ignore_ascii_case(column)))
+        .ok_or_else(|| Error::Import(format!("no column '{}' in the statement", column)));
+}
+
+fn field(record: &StringRecord, index: usize, line: usize) -> Result<&str> {
+    return record.get(index)
+        .map(|f| f.trim())
+        .ok_or_else(|| Error::Import(format!("line {} : missing column {}", line, index + 1)));
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::Path;
+use chrono::NaiveDate;
+use crate::error::{Error, Result};
+use crate::import::{occurrence_references, parse_guessed_amount};
+use crate::structs::ExpenseType;
+
+// ------------------------------------------------------------
+// LEDGER / HLEDGER JOURNALS
+// ------------------------------------------------------------
+/// Whether a posting goes to an expense or an income account.
+#[derive(Debug, Clone)]
+pub enum PostingKind {
+    Expense(ExpenseType),
+    Income,
+}
+
+/// A posting of a journal transaction to an expense or income account.
+#[derive(Debug)]
+pub struct JournalPosting {
+    /// Identifies the posting across imports, from its date, account, amount and description.
+    pub reference: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub account: String,
+    pub kind: PostingKind,
+    /// In cents : spent for expenses, received for incomes.
+    pub amount: i64,
+}
+
+/// A transaction being read : its date, description and postings, the amount of which may be left out.
+struct Draft {
+    date: NaiveDate,
+    description: String,
+    postings: Vec<(String, Option<i64>)>,
+}
+
+/// Read the postings of a ledger or hledger journal that go to expense or income accounts.
+///
+/// Expense accounts start with "expenses", income accounts with "income" or "revenues".
+/// The type of an expense line comes from the account : a "fixed", "estimated" (or "variable")
+/// or "unplanned" level, "estimated" by default. Directives, periodic and automated transactions
+/// are left out, as are the commodities and prices of amounts.
+pub fn read_journal<P: AsRef<Path>>(path: P) -> Result<Vec<JournalPosting>> {
+    let content = fs::read_to_string(path)?;
+
+    let mut drafts: Vec<Draft> = Vec::new();
+    let mut current: Option<Draft> = None;
+    let mut in_comment_block = false;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if in_comment_block {
+            in_comment_block = trimmed != "end comment";
+            continue;
+        }
+
+        if trimmed.is_empty() || (trimmed.starts_with([';', '#', '*', '%']) && !line.starts_with([' ', '\t'])) {
+            continue;
+        }
+
+        // Indented lines belong to the transaction or directive above.
+        if line.starts_with([' ', '\t']) {
+            if let Some(draft) = current.as_mut() {
+                if !trimmed.starts_with(';') {
+                    draft.postings.push(parse_posting(trimmed, index + 1)?);
+                }
+            }
+
+            continue;
+        }
+
+        drafts.extend(current.take());
+
+        if trimmed == "comment" {
+            in_comment_block = true;
+        }
+        else if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            current = Some(parse_header(trimmed, index + 1)?);
+        }
+    }
+
+    drafts.extend(current);
+
+    let mut rows = Vec::new();
+
+    for draft in drafts {
+        let missing = draft.postings.iter().filter(|(_, amount)| amount.is_none()).count();
+
+        if missing > 1 {
+            return Err(Error::Import(format!("transaction '{}' of {} leaves out several amounts", draft.description, draft.date)));
+        }
+
+        // A posting without an amount balances the others.
+        let balance: i64 = -draft.postings.iter().filter_map(|(_, amount)| *amount).sum::<i64>();
+
+        for (account, amount) in &draft.postings {
+            let amount = amount.unwrap_or(balance);
+
+            let Some(kind) = account_kind(account) else { continue };
+            let amount = match kind {
+                PostingKind::Expense(_) => amount,
+                PostingKind::Income => -amount,
+            };
+
+            rows.push((draft.date, draft.description.clone(), account.clone(), kind, amount));
+        }
+    }
+
+    let keys = rows.iter()
+        .map(|(date, description, account, _, amount)| format!("journal:{}|{}|{}|{}", date, account, amount, description))
+        .collect();
+
+    let postings = rows.into_iter()
+        .zip(occurrence_references(keys))
+        .map(|((date, description, account, kind, amount), reference)| JournalPosting { reference, date, description, account, kind, amount })
+        .collect();
+
+    return Ok(postings);
+}
+
+/// Kind of an account, if it is an expense or income one.
+pub fn account_kind(account: &str) -> Option<PostingKind> {
+    let levels: Vec<String> = account.split(':').map(|l| l.trim().to_lowercase()).collect();
+
+    return match levels.first().map(|l| l.as_str()) {
+        Some("expenses") | Some("expense") => {
+            let expense_type = levels.iter().skip(1).find_map(|level| match level.as_str() {
+                "fixed" => Some(ExpenseType::FIXED),
+                "estimated" | "variable" => Some(ExpenseType::ESTIMATED),
+                "unplanned" => Some(ExpenseType::UNPLANNED),
+                _ => None,
+            });
+
+            Some(PostingKind::Expense(expense_type.unwrap_or(ExpenseType::ESTIMATED)))
+        },
+        Some("income") | Some("revenue") | Some("revenues") => Some(PostingKind::Income),
+        _ => None,
+    };
+}
+
+/// Default label of an account : its last level, e.g. "rent" for "expenses:fixed:rent".
+pub fn account_label(account: &str) -> String {
+    return account.rsplit(':').next().unwrap_or(account).trim().to_string();
+}
+
+/// Parse a transaction header, e.g. `2024-01-05=2024-01-06 * (42) Groceries ; comment`.
+fn parse_header(line: &str, line_number: usize) -> Result<Draft> {
+    let line = line.split(';').next().unwrap_or(line);
+    let (date_token, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let primary = date_token.split('=').next().unwrap_or(date_token);
+
+    let date = ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d"].iter()
+        .find_map(|format| NaiveDate::parse_from_str(primary, format).ok())
+        .ok_or_else(|| Error::Import(format!("line {} : invalid date '{}'", line_number, date_token)))?;
+
+    let mut description = rest.trim().trim_start_matches(['*', '!']).trim();
+
+    if description.starts_with('(') {
+        description = description.split_once(')').map_or("", |(_, d)| d).trim();
+    }
+
+    return Ok(Draft { date, description: description.to_string(), postings: Vec::new() });
+}
+
+/// Parse a posting, e.g. `expenses:fixed:rent  €800.00 ; memo`. The account ends at two spaces or a tab.
+fn parse_posting(line: &str, line_number: usize) -> Result<(String, Option<i64>)> {
+    let line = line.split(';').next().unwrap_or(line).trim();
+    let line = line.trim_start_matches(['*', '!']).trim_start();
+
+    let end = [line.find("  "), line.find('\t')].into_iter().flatten().min().unwrap_or(line.len());
+    let account = line[..end].trim().trim_matches(['(', ')', '[', ']']).to_string();
+
+    // Costs (@) and balance assertions (=) are left out.
+    let raw = line[end..].split(['@', '=']).next().unwrap_or("").trim();
+
+    if raw.is_empty() {
+        return Ok((account, None));
+    }
+
+    // Drop the commodity, quoted or not, wherever it stands.
+    let unquoted: String = raw.split('"').step_by(2).collect();
+    let figures: String = unquoted.chars().filter(|c| c.is_ascii_digit() || ".,-+".contains(*c)).collect();
+
+    let amount = parse_guessed_amount(&figures)
+        .ok_or_else(|| Error::Import(format!("line {} : invalid amount '{}'", line_number, raw)))?;
+
+    return Ok((account, Some(amount)));
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use chrono::NaiveDate;
+use serde::Serialize;
+use crate::error::{Error, Result};
+use crate::currencies::precision;
+use crate::settings::REPORTING_CURRENCY;
+use crate::utils::parse_amount;
+
+pub mod camt;
+pub mod csv;
+pub mod journal;
+pub mod mt940;
+pub mod ofx;
+pub mod qif;
+pub mod rates;
+pub mod worksheet;
+
+// ------------------------------------------------------------
+// IMPORT
+// ------------------------------------------------------------
+/// One booked entry of a bank statement.
+#[derive(Debug)]
+pub struct StatementEntry {
+    /// Identifies the entry across imports, so that importing a statement twice does not count it twice.
+    pub reference: String,
+    pub date: NaiveDate,
+    pub amount: i64, // in cents, negative for money going out.
+    pub description: String,
+}
+
+/// Rules picking the expense line or income an entry belongs to, from its description.
+/// Read from a file of `pattern = label` lines : the first pattern found in the description,
+/// ignoring case, gives the label. Lines starting with '#' are comments.
+#[derive(Debug, Default)]
+pub struct LabelRules {
+    rules: Vec<(String, String)>,
+}
+
+impl LabelRules {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LabelRules> {
+        let content = fs::read_to_string(path)?;
+        let mut rules = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once('=') {
+                Some((pattern, label)) => rules.push((pattern.trim().to_lowercase(), label.trim().to_string())),
+                None => return Err(Error::Import(format!("line {} of the rules file : expected 'pattern = label'", index + 1))),
+            }
+        }
+
+        return Ok(LabelRules { rules });
+    }
+
+    /// Label matching a description, if any rule applies.
+    pub fn label_for(&self, description: &str) -> Option<&str> {
+        let description = description.to_lowercase();
+
+        return self.rules.iter()
+            .find(|(pattern, _)| description.contains(pattern.as_str()))
+            .map(|(_, label)| label.as_str());
+    }
+}
+
+/// What an import did to one expense line or income.
+#[derive(Debug, Default, Serialize)]
+pub struct LineSummary {
+    pub count: u32,
+    pub total: i64, // in cents.
+    pub created: bool,
+}
+
+/// What an import did, by expense line and income label.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub expenses: BTreeMap<String, LineSummary>,
+    pub incomes: BTreeMap<String, LineSummary>,
+    /// Entries already imported before.
+    pub duplicates: u32,
+    /// Entries dated outside of the period.
+    pub outside_period: u32,
+    /// Periods created to hold the entries, for imports spanning several periods.
+    pub created_periods: Vec<u32>,
+}
+
+impl ImportSummary {
+    pub fn count_expense(&mut self, label: &str, amount: i64, created: bool) {
+        count(self.expenses.entry(label.to_string()).or_default(), amount, created);
+    }
+
+    pub fn count_income(&mut self, label: &str, amount: i64, created: bool) {
+        count(self.incomes.entry(label.to_string()).or_default(), amount, created);
+    }
+}
+
+fn count(line: &mut LineSummary, amount: i64, created: bool) {
+    line.count += 1;
+    line.total += amount;
+    line.created |= created;
+}
+
+/// Parse a statement amount such as "-1 234,56" into signed cents.
+pub fn parse_statement_amount(raw: &str, decimal_separator: char) -> Option<i64> {
+    let thousands_separator = if decimal_separator == '.' { ',' } else { '.' };
+    return parse_amount(raw, decimal_separator, Some(thousands_separator), &[], precision(&REPORTING_CURRENCY)).ok();
+}
+
+/// Parse a statement amount whose decimal separator is not known in advance :
+/// the last '.' or ',' is the decimal separator if at most two digits follow it.
+pub fn parse_guessed_amount(raw: &str) -> Option<i64> {
+    let decimal_separator = match raw.rfind(['.', ',']) {
+        Some(position) => {
+            let separator = raw[position..].chars().next()?;
+            let decimals = raw[position + 1..].trim().len();
+
+            match (decimals <= 2, separator) {
+                (true, s) => s,
+                (false, '.') => ',',
+                (false, _) => '.',
+            }
+        },
+        None => '.',
+    };
+
+    return parse_statement_amount(raw, decimal_separator);
+}
+
+/// Number the entries sharing the same key, so that identical entries of a statement
+/// (two coffees on the same day) get distinct but stable references.
+pub fn occurrence_references(keys: Vec<String>) -> Vec<String> {
+    let mut seen: BTreeMap<String, u32> = BTreeMap::new();
+
+    return keys.into_iter()
+        .map(|key| {
+            let count = seen.entry(key.clone()).or_insert(0);
+            *count += 1;
+            format!("{}#{}", key, count)
+        })
+        .collect();
+}
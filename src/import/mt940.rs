@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::Path;
+use chrono::{Datelike, NaiveDate};
+use crate::error::{Error, Result};
+use crate::import::{occurrence_references, parse_statement_amount, StatementEntry};
+
+// ------------------------------------------------------------
+// SWIFT MT940 STATEMENTS
+// ------------------------------------------------------------
+/// Read the entries of an MT940 statement, from its :61: lines and the :86: lines following them.
+/// MT940 only holds booked entries. They are referenced by the bank reference of their :61: line,
+/// or the customer one, scoped by account.
+pub fn read_statement<P: AsRef<Path>>(path: P) -> Result<Vec<StatementEntry>> {
+    let content = String::from_utf8_lossy(&fs::read(path)?).to_string();
+
+    let mut account = String::new();
+    let mut rows: Vec<(String, NaiveDate, i64, String)> = Vec::new();
+
+    for (tag, value) in fields(&content) {
+        match tag.as_str() {
+            "25" => account = value.trim().to_string(),
+            "61" => rows.push(parse_statement_line(&value, &account)?),
+            "86" => {
+                if let Some(row) = rows.last_mut() {
+                    let description = describe(&value);
+
+                    if !description.is_empty() {
+                        row.3 = description;
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let keys = rows.iter().map(|(key, _, _, _)| key.clone()).collect();
+
+    let entries = rows.into_iter()
+        .zip(occurrence_references(keys))
+        .map(|((_, date, amount, description), reference)| StatementEntry { reference, date, amount, description })
+        .collect();
+
+    return Ok(entries);
+}
+
+/// Split a statement into its `:tag:value` fields. A value goes on until the next tag.
+fn fields(content: &str) -> Vec<(String, String)> {
+    let mut res: Vec<(String, String)> = Vec::new();
+
+    for line in content.lines() {
+        // Skip the SWIFT envelope, e.g. {1:F01...}{2:...}{4:
+        let line = match line.rfind("{4:") {
+            Some(position) => &line[position + 3..],
+            None => line,
+        };
+        let line = line.trim_end();
+
+        if line.is_empty() || line.starts_with('-') || line.starts_with('{') {
+            continue;
+        }
+
+        let tag = line.strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| tag.len() <= 3 && tag.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        match (tag, res.last_mut()) {
+            (Some((tag, value)), _) => res.push((tag.to_string(), value.to_string())),
+            (None, Some((_, value))) => {
+                value.push('\n');
+                value.push_str(line);
+            },
+            (None, None) => {},
+        }
+    }
+
+    return res;
+}
+
+/// Parse a :61: statement line, e.g. `2610051005D12,50NMSCNONREF//B5K0131\nLIDL`.
+fn parse_statement_line(value: &str, account: &str) -> Result<(String, NaiveDate, i64, String)> {
+    let (line, supplementary) = value.split_once('\n').unwrap_or((value, ""));
+    let invalid = || Error::Import(format!("invalid :61: line '{}'", line));
+
+    let value_date = line.get(0..6)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%y%m%d").ok())
+        .ok_or_else(invalid)?;
+    let mut rest = &line[6..];
+
+    // The booking date is optional, and has no year : it may fall in the year before or after the value date.
+    let mut date = value_date;
+
+    if let Some(entry_date) = rest.get(0..4).filter(|d| d.chars().all(|c| c.is_ascii_digit())) {
+        let month: u32 = entry_date[0..2].parse().map_err(|_| invalid())?;
+        let day: u32 = entry_date[2..4].parse().map_err(|_| invalid())?;
+
+        date = [value_date.year(), value_date.year() - 1, value_date.year() + 1].iter()
+            .filter_map(|year| NaiveDate::from_ymd_opt(*year, month, day))
+            .min_by_key(|d| (*d - value_date).num_days().abs())
+            .ok_or_else(invalid)?;
+        rest = &rest[4..];
+    }
+
+    // Reversals of a credit take money out, reversals of a debit bring it back.
+    let mark = ["RC", "RD", "C", "D"].into_iter()
+        .find(|m| rest.starts_with(m))
+        .ok_or_else(invalid)?;
+    let sign = if mark == "C" || mark == "RD" { 1 } else { -1 };
+    rest = &rest[mark.len()..];
+
+    // Optional funds code.
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_length = rest.find(|c: char| !c.is_ascii_digit() && c != ',').unwrap_or(rest.len());
+    let amount = parse_statement_amount(&rest[..amount_length], ',').ok_or_else(invalid)? * sign;
+
+    // Transaction type (N, F or S and three characters), then the customer and bank references.
+    let references = rest.get(amount_length + 4..).unwrap_or("");
+    let (customer, bank) = references.split_once("//").unwrap_or((references, ""));
+
+    let reference = match (bank.trim(), customer.trim()) {
+        (bank, _) if !bank.is_empty() => bank.to_string(),
+        (_, customer) if !customer.is_empty() && customer != "NONREF" => customer.to_string(),
+        _ => String::new(),
+    };
+
+    let description = supplementary.trim().to_string();
+
+    let key = match reference.is_empty() {
+        true => format!("mt940:{}:{}|{}|{}", account, date, amount, description),
+        false => format!("mt940:{}:{}", account, reference),
+    };
+
+    return Ok((key, date, amount, description));
+}
+
+/// Description of an entry from its :86: field. Structured fields, as in `166?00SEPA?20Invoice 12?32ACME`,
+/// give the counterparty name (?32, ?33) or the purpose (?20 to ?29). Free text is kept as is.
+fn describe(value: &str) -> String {
+    let value = value.replace('\n', "");
+
+    if value.len() < 4 || value.as_bytes()[3] != b'?' {
+        return value.split_whitespace().collect::<Vec<&str>>().join(" ");
+    }
+
+    let mut name = String::new();
+    let mut purpose = String::new();
+
+    for subfield in value.split('?').skip(1) {
+        let code = subfield.get(0..2).unwrap_or("");
+        let text = subfield.get(2..).unwrap_or("");
+
+        match code {
+            "32" | "33" => name.push_str(text),
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" => purpose.push_str(text),
+            _ => {},
+        }
+    }
+
+    return if name.trim().is_empty() { purpose.trim().to_string() } else { name.trim().to_string() };
+}
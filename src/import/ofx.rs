@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::Path;
+use chrono::NaiveDate;
+use crate::error::{Error, Result};
+use crate::import::{parse_guessed_amount, StatementEntry};
+
+// ------------------------------------------------------------
+// OFX / QFX STATEMENTS
+// ------------------------------------------------------------
+/// Fields of a <STMTTRN> block.
+#[derive(Default)]
+struct OfxTransaction {
+    posted: Option<String>,
+    amount: Option<String>,
+    fitid: Option<String>,
+    name: Option<String>,
+    memo: Option<String>,
+}
+
+/// Read the entries of an OFX or QFX statement, SGML (1.x) or XML (2.x) flavoured.
+/// Entries are referenced by their FITID, scoped by the account of their statement.
+pub fn read_statement<P: AsRef<Path>>(path: P) -> Result<Vec<StatementEntry>> {
+    let content = String::from_utf8_lossy(&fs::read(path)?).to_string();
+
+    let mut account = String::new();
+    let mut current: Option<OfxTransaction> = None;
+    let mut entries = Vec::new();
+
+    // Every element starts with '<'. SGML files may omit the closing tags of leaf elements.
+    for chunk in content.split('<').skip(1) {
+        let (tag, value) = chunk.split_once('>').unwrap_or((chunk, ""));
+        let tag = tag.trim().to_uppercase();
+        let value = decode(value.trim());
+
+        match tag.as_str() {
+            "STMTTRN" => current = Some(OfxTransaction::default()),
+            "/STMTTRN" => {
+                if let Some(transaction) = current.take() {
+                    entries.push(to_entry(transaction, &account)?);
+                }
+            },
+            // A file may hold several statements, each with its own account.
+            "STMTRS" | "CCSTMTRS" => account.clear(),
+            "ACCTID" if current.is_none() => account = value,
+            _ => {
+                if let Some(transaction) = current.as_mut() {
+                    let field = match tag.as_str() {
+                        "DTPOSTED" => &mut transaction.posted,
+                        "TRNAMT" => &mut transaction.amount,
+                        "FITID" => &mut transaction.fitid,
+                        "NAME" | "PAYEE" => &mut transaction.name,
+                        "MEMO" => &mut transaction.memo,
+                        _ => continue,
+                    };
+
+                    if !value.is_empty() {
+                        *field = Some(value);
+                    }
+                }
+            }
+        }
+    }
+
+    return Ok(entries);
+}
+
+fn to_entry(transaction: OfxTransaction, account: &str) -> Result<StatementEntry> {
+    let fitid = transaction.fitid
+        .ok_or_else(|| Error::Import("a transaction has no FITID".to_string()))?;
+
+    let posted = transaction.posted
+        .ok_or_else(|| Error::Import(format!("transaction {} has no DTPOSTED", fitid)))?;
+
+    // Dates look like 20261005, 20261005120000 or 20261005120000.000[+1:CET] : only the day matters.
+    let date = posted.get(0..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| Error::Import(format!("transaction {} : invalid date '{}'", fitid, posted)))?;
+
+    let raw_amount = transaction.amount.unwrap_or_default();
+    let amount = parse_guessed_amount(&raw_amount)
+        .ok_or_else(|| Error::Import(format!("transaction {} : invalid amount '{}'", fitid, raw_amount)))?;
+
+    let description = transaction.name
+        .or(transaction.memo)
+        .unwrap_or_else(|| fitid.clone());
+
+    return Ok(StatementEntry {
+        reference: format!("ofx:{}:{}", account, fitid),
+        date,
+        amount,
+        description,
+    });
+}
+
+/// Decode the few character entities OFX files use.
+fn decode(value: &str) -> String {
+    return value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+use chrono::NaiveDate;
+use crate::error::{Error, Result};
+use crate::import::{occurrence_references, parse_guessed_amount, StatementEntry};
+
+// ------------------------------------------------------------
+// QIF STATEMENTS
+// ------------------------------------------------------------
+/// Fields of a QIF record, up to its closing '^'.
+#[derive(Default)]
+struct QifRecord {
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    number: Option<String>,
+}
+
+/// Read the entries of a QIF statement. Dates are read with the given chrono format, e.g. "%d/%m/%Y".
+/// QIF has no transaction id : entries are referenced by their check number when there is one,
+/// by their date, amount and payee otherwise.
+pub fn read_statement<P: AsRef<Path>>(path: P, date_format: &str) -> Result<Vec<StatementEntry>> {
+    let content = String::from_utf8_lossy(&fs::read(path)?).to_string();
+
+    let mut record = QifRecord::default();
+    let mut rows = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim_end();
+        let mut chars = line.chars();
+
+        let (code, value) = match chars.next() {
+            Some(c) => (c, chars.as_str().trim().to_string()),
+            None => continue,
+        };
+
+        match code {
+            '!' => {},
+            '^' if record.date.is_some() || record.amount.is_some() => {
+                rows.push(to_row(std::mem::take(&mut record), date_format, index + 1)?);
+            },
+            'D' => record.date = Some(value),
+            'T' | 'U' => record.amount = Some(value),
+            'P' => record.payee = Some(value),
+            'M' => record.memo = Some(value),
+            'N' => record.number = Some(value),
+            _ => {},
+        }
+    }
+
+    let keys = rows.iter()
+        .map(|(date, amount, description, number)| match number {
+            Some(n) => format!("qif:N{}|{}", n, amount),
+            None => format!("qif:{}|{}|{}", date, amount, description),
+        })
+        .collect();
+
+    let entries = rows.into_iter()
+        .zip(occurrence_references(keys))
+        .map(|((date, amount, description, _), reference)| StatementEntry { reference, date, amount, description })
+        .collect();
+
+    return Ok(entries);
+}
+
+/// The date format with `%y` instead of `%Y` when the date gives its year with two digits,
+/// since `%Y` would read "26" as the year 26.
+fn with_year_width(date: &str, date_format: &str) -> String {
+    let specifiers: Vec<char> = date_format.split('%').skip(1).filter_map(|s| s.chars().next()).collect();
+    let fields: Vec<&str> = date.split(|c: char| !c.is_ascii_digit()).filter(|f| !f.is_empty()).collect();
+
+    return match specifiers.iter().position(|c| *c == 'Y').and_then(|i| fields.get(i)) {
+        Some(year) if year.len() == 2 => date_format.replace("%Y", "%y"),
+        _ => date_format.to_string(),
+    };
+}
+
+fn to_row(record: QifRecord, date_format: &str, line: usize) -> Result<(NaiveDate, i64, String, Option<String>)> {
+    // Quicken writes years as 10/5'26 and pads days with spaces, as in 10/ 5/26.
+    let raw_date = record.date.unwrap_or_default();
+    let normalized = raw_date.replace('\'', "/").replace(' ', "");
+
+    let date = NaiveDate::parse_from_str(&normalized, &with_year_width(&normalized, date_format))
+        .map_err(|_| Error::Import(format!("line {} : '{}' does not match the date format {}", line, raw_date, date_format)))?;
+
+    let raw_amount = record.amount.unwrap_or_default();
+    let amount = parse_guessed_amount(&raw_amount)
+        .ok_or_else(|| Error::Import(format!("line {} : '{}' is not a valid amount", line, raw_amount)))?;
+
+    let description = record.payee.or(record.memo).unwrap_or_default();
+    let number = record.number.filter(|n| !n.is_empty());
+
+    return Ok((date, amount, description, number));
+}
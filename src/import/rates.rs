@@ -0,0 +1,67 @@
+use std::path::Path;
+use chrono::NaiveDate;
+use ::csv::{ReaderBuilder, Trim};
+use crate::currencies::code_of;
+use crate::error::{Error, Result};
+use crate::structs::ExchangeRate;
+
+// ------------------------------------------------------------
+// EXCHANGE RATES
+// ------------------------------------------------------------
+/// Date formats of the ECB files : "2024-05-17" in the history, "17 May 2024" in the daily file.
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d %B %Y", "%d %b %Y"];
+
+/// Read an ECB-style CSV file of exchange rates against the euro, such as eurofxref.csv or eurofxref-hist.csv :
+/// a date column, then one column per currency code. Missing rates ("N/A" or empty) are skipped.
+pub fn read_rates<P: AsRef<Path>>(path: P) -> Result<Vec<ExchangeRate>> {
+    let mut reader = ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| Error::Import(e.to_string()))?;
+
+    let headers = reader.headers().map_err(|e| Error::Import(e.to_string()))?.clone();
+
+    let currencies = headers.iter()
+        .skip(1)
+        .map(|header| match header {
+            "" => Ok(None),
+            _ => code_of(header)
+                .map(Some)
+                .ok_or_else(|| Error::Import(format!("'{}' is not a currency code", header))),
+        })
+        .collect::<Result<Vec<Option<String>>>>()?;
+
+    let mut rates = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| Error::Import(e.to_string()))?;
+        let line = index + 2;
+
+        let raw_date = record.get(0).unwrap_or_default();
+
+        if raw_date.is_empty() {
+            continue;
+        }
+
+        let date = DATE_FORMATS.iter()
+            .find_map(|format| NaiveDate::parse_from_str(raw_date, format).ok())
+            .ok_or_else(|| Error::Import(format!("line {} : '{}' is not a date", line, raw_date)))?;
+
+        for (currency, raw_rate) in currencies.iter().zip(record.iter().skip(1)) {
+            let Some(currency) = currency else { continue };
+
+            if raw_rate.is_empty() || raw_rate.eq_ignore_ascii_case("N/A") {
+                continue;
+            }
+
+            let rate = raw_rate.parse::<f64>().ok()
+                .filter(|r| r.is_finite() && *r > 0.0)
+                .ok_or_else(|| Error::Import(format!("line {} : '{}' is not a valid rate for {}", line, raw_rate, currency)))?;
+
+            rates.push(ExchangeRate { date, currency: currency.clone(), rate });
+        }
+    }
+
+    return Ok(rates);
+}
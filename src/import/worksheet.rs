@@ -0,0 +1,249 @@
+use std::path::Path;
+use calamine::{open_workbook_auto, Data, DataType, Reader};
+use chrono::{Months, NaiveDate};
+use ::csv::ReaderBuilder;
+use crate::currencies::precision;
+use crate::error::{Error, Result};
+use crate::import::parse_guessed_amount;
+use crate::settings::REPORTING_CURRENCY;
+use crate::structs::ExpenseType;
+
+// ------------------------------------------------------------
+// LEGACY WORKSHEETS
+// ------------------------------------------------------------
+/// Whether a worksheet row is an income or an expense line.
+#[derive(Debug, Clone)]
+pub enum LineKind {
+    Income,
+    Expense(ExpenseType),
+}
+
+/// A label / estimate / spent row of a worksheet.
+/// For incomes, the estimate is the expected amount and the spent column what was received.
+#[derive(Debug)]
+pub struct WorksheetLine {
+    pub kind: LineKind,
+    pub label: String,
+    pub estimate: Option<i64>, // in cents.
+    pub spent: Option<i64>, // in cents.
+}
+
+/// The rows of one period of a worksheet.
+#[derive(Debug)]
+pub struct WorksheetPeriod {
+    /// Sheet the period comes from, to point at it in error messages.
+    pub sheet: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub lines: Vec<WorksheetLine>,
+}
+
+/// A worksheet cell, whatever the file format.
+enum Cell {
+    Empty,
+    Number(f64),
+    Date(NaiveDate),
+    Text(String),
+}
+
+/// Formats of the sheet names and date rows giving the start of a period, e.g. "2024-01" or "January 2024".
+const PERIOD_FORMATS: &[&str] = &["%Y-%m-%d", "%d/%m/%Y", "%Y-%m", "%m/%Y", "%m-%Y", "%B %Y", "%b %Y", "%Y %B", "%Y %b"];
+
+/// Read a three-column worksheet (.xlsx, .xls, .ods or .csv), the way Ebenezer budgets were kept before it.
+///
+/// Each sheet is a period, starting on the date its name holds. A row holding a date alone in the first
+/// column starts a new period within the sheet. Periods with no date follow the previous one by a month,
+/// the first one starting on `start` if given.
+/// Rows holding a title alone switch the kind of the following lines : income, fixed, variable or estimated,
+/// unplanned. Lines before any title are estimated expenses. Totals and balances are left out.
+/// Each period ends the day before the next one starts, or a month after it started for the last one.
+pub fn read_worksheet<P: AsRef<Path>>(path: P, start: Option<NaiveDate>) -> Result<Vec<WorksheetPeriod>> {
+    let path = path.as_ref();
+    let sheets = match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("csv") => vec![read_csv(path)?],
+        _ => read_workbook(path)?,
+    };
+
+    let mut blocks: Vec<(String, Option<NaiveDate>, Vec<WorksheetLine>)> = Vec::new();
+
+    for (name, rows) in sheets {
+        let mut current = (name.clone(), parse_period_date(&name), Vec::new());
+        let mut kind = LineKind::Expense(ExpenseType::ESTIMATED);
+
+        for row in rows {
+            let label = row.first().unwrap_or(&Cell::Empty);
+            let estimate = row.get(1).and_then(amount);
+            let spent = row.get(2).and_then(amount);
+
+            if estimate.is_none() && spent.is_none() {
+                if let Some(date) = row_date(label) {
+                    blocks.push(std::mem::replace(&mut current, (name.clone(), Some(date), Vec::new())));
+                }
+                else if let Some(k) = section(label) {
+                    kind = k;
+                }
+
+                continue;
+            }
+
+            let label = match label {
+                Cell::Text(t) => t.trim().to_string(),
+                _ => continue,
+            };
+
+            let lowercase = label.to_lowercase();
+
+            if label.is_empty() || lowercase.starts_with("total") || lowercase.starts_with("balance") {
+                continue;
+            }
+
+            current.2.push(WorksheetLine { kind: kind.clone(), label, estimate, spent });
+        }
+
+        blocks.push(current);
+    }
+
+    // Give a start date to the periods that have none, then close each one where the next begins.
+    let mut previous_start: Option<NaiveDate> = None;
+    let mut res: Vec<WorksheetPeriod> = Vec::new();
+
+    for (sheet, date, lines) in blocks.into_iter().filter(|b| !b.2.is_empty()) {
+        let start_date = match (date, previous_start) {
+            (Some(d), _) => d,
+            (None, Some(p)) => p + Months::new(1),
+            (None, None) => start.ok_or_else(|| Error::Import(format!("no date for the period of sheet '{}', name it after its month or use --start", sheet)))?,
+        };
+
+        previous_start = Some(start_date);
+        res.push(WorksheetPeriod { sheet, start_date, end_date: start_date, lines });
+    }
+
+    res.sort_by_key(|p| p.start_date);
+
+    for index in 0..res.len() {
+        let start_date = res[index].start_date;
+
+        res[index].end_date = match res.get(index + 1) {
+            Some(next) if next.start_date == start_date => {
+                return Err(Error::Import(format!("sheets '{}' and '{}' both start on {}", res[index].sheet, next.sheet, start_date)));
+            },
+            Some(next) => next.start_date.pred_opt().unwrap_or(start_date),
+            None => (start_date + Months::new(1)).pred_opt().unwrap_or(start_date),
+        };
+    }
+
+    return Ok(res);
+}
+
+fn read_workbook(path: &Path) -> Result<Vec<(String, Vec<Vec<Cell>>)>> {
+    let mut workbook = open_workbook_auto(path).map_err(|e| Error::Import(e.to_string()))?;
+    let mut res = Vec::new();
+
+    for name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&name).map_err(|e| Error::Import(e.to_string()))?;
+
+        let rows = range.rows()
+            .map(|row| row.iter().map(to_cell).collect())
+            .collect();
+
+        res.push((name, rows));
+    }
+
+    return Ok(res);
+}
+
+/// A CSV worksheet is a single sheet, named after the file. Fields are separated by ';' or ','.
+fn read_csv(path: &Path) -> Result<(String, Vec<Vec<Cell>>)> {
+    let content = std::fs::read_to_string(path)?;
+    let delimiter = if content.lines().next().unwrap_or("").contains(';') { b';' } else { b',' };
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let mut rows = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| Error::Import(e.to_string()))?;
+
+        rows.push(record.iter()
+            .map(|field| match field.trim() {
+                "" => Cell::Empty,
+                text => Cell::Text(text.to_string()),
+            })
+            .collect());
+    }
+
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    return Ok((name, rows));
+}
+
+fn to_cell(data: &Data) -> Cell {
+    return match data {
+        Data::Int(i) => Cell::Number(*i as f64),
+        Data::Float(f) => Cell::Number(*f),
+        Data::DateTime(_) | Data::DateTimeIso(_) => data.as_date().map_or(Cell::Empty, Cell::Date),
+        Data::String(s) if s.trim().is_empty() => Cell::Empty,
+        Data::String(s) => Cell::Text(s.clone()),
+        _ => Cell::Empty,
+    };
+}
+
+/// Amount of a cell in the smallest unit of the reporting currency. Currency symbols around the figures are ignored.
+fn amount(cell: &Cell) -> Option<i64> {
+    return match cell {
+        Cell::Number(n) => Some((n * 10f64.powi(precision(&REPORTING_CURRENCY) as i32)).round() as i64),
+        Cell::Text(t) => {
+            let figures = t.trim_matches(|c: char| !c.is_ascii_digit() && c != '-' && c != '+');
+            if figures.is_empty() { None } else { parse_guessed_amount(figures) }
+        },
+        _ => None,
+    };
+}
+
+/// Start date held by a sheet name or a date row, e.g. "2024-01" or "January 2024".
+fn parse_period_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+
+    return PERIOD_FORMATS.iter().find_map(|format| {
+        NaiveDate::parse_from_str(text, format)
+            .or_else(|_| NaiveDate::parse_from_str(&format!("{} 1", text), &format!("{} %d", format)))
+            .ok()
+    });
+}
+
+fn row_date(cell: &Cell) -> Option<NaiveDate> {
+    return match cell {
+        Cell::Date(d) => Some(*d),
+        Cell::Text(t) => parse_period_date(t),
+        _ => None,
+    };
+}
+
+/// Kind of the lines following a title row.
+fn section(cell: &Cell) -> Option<LineKind> {
+    let title = match cell {
+        Cell::Text(t) => t.to_lowercase(),
+        _ => return None,
+    };
+
+    if title.contains("income") || title.contains("revenue") {
+        return Some(LineKind::Income);
+    }
+
+    if title.contains("unplanned") {
+        return Some(LineKind::Expense(ExpenseType::UNPLANNED));
+    }
+
+    if title.contains("fixed") {
+        return Some(LineKind::Expense(ExpenseType::FIXED));
+    }
+
+    if title.contains("variable") || title.contains("estimated") {
+        return Some(LineKind::Expense(ExpenseType::ESTIMATED));
+    }
+
+    return None;
+}
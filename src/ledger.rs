@@ -0,0 +1,1685 @@
+use std::collections::HashSet;
+use std::path::Path;
+use chrono::{Datelike, Local, Months, NaiveDate};
+use rusqlite::{Connection, OptionalExtension, ToSql};
+use rusqlite::types::ToSqlOutput;
+use crate::categories::{parse_path, CategoryTree, PATH_SEPARATOR};
+use crate::currencies::Rates;
+use crate::error::{Error, Result};
+use crate::export::PeriodData;
+use crate::history::Changeset;
+use crate::import::{ImportSummary, LabelRules, StatementEntry};
+use crate::import::journal::{account_label, JournalPosting, PostingKind};
+use crate::import::worksheet::{LineKind, WorksheetPeriod};
+use crate::migrations::{migrate, schema_version};
+use crate::schedule::Schedule;
+use crate::settings::{get_dbfile, REPORTING_CURRENCY};
+use crate::structs::{Category, ExchangeRate, Log, Period, Income, Expense, ExpenseType, Transaction};
+use crate::utils::print_amount;
+
+// ------------------------------------------------------------
+// LEDGER
+// ------------------------------------------------------------
+/// A budget database. Every mutation is recorded in the audit log.
+pub struct Ledger {
+    conn: Connection,
+}
+
+impl Ledger {
+    /// Open the database at the configured location.
+    pub fn open_default() -> Result<Ledger> {
+        return Ledger::open(get_dbfile());
+    }
+
+    /// Open a database, bring its schema up to date and make sure a period is ongoing.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Ledger> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+
+        let ledger = Ledger { conn };
+        ledger.fill_currencies()?;
+
+        if ledger.get_current_period()? == 0 {
+            let id = ledger.insert_period(&mut Changeset::default(), Local::now().date_naive(), None)?;
+            ledger.create_log(Some(id), "START_PERIOD", &[id.to_string()], &mut Changeset::default())?;
+        }
+
+        Ok(ledger)
+    }
+
+    /// The schema version of the underlying database.
+    pub fn schema_version(&self) -> Result<u32> {
+        return Ok(schema_version(&self.conn)?);
+    }
+
+    /// Give the reporting currency to the amounts recorded before currencies were.
+    fn fill_currencies(&self) -> Result<()> {
+        for table in ["incomes", "expenses", "transactions"] {
+            self.conn.execute(
+                &format!("UPDATE {} SET currency = ? WHERE currency IS NULL", table),
+                [&*REPORTING_CURRENCY],
+            )?;
+        }
+
+        self.conn.execute("UPDATE transactions SET line_amount = amount WHERE line_amount IS NULL", [])?;
+
+        Ok(())
+    }
+
+    // ------------------------------------------------------------
+    // PERIODS
+    // ------------------------------------------------------------
+    /// Id of the ongoing period, the one that started last, or 0 if there is none yet.
+    pub fn get_current_period(&self) -> Result<u32> {
+        let mut stmt = self.conn.prepare(
+            "SELECT ifnull((SELECT p.id FROM periods p ORDER BY p.start_date desc, p.id desc LIMIT 1), 0)"
+        )?;
+
+        let mut rows = stmt.query([])?;
+        let mut res = 0;
+
+        if let Some(row) = rows.next()? {
+            res = row.get(0)?;
+        }
+
+        Ok(res)
+    }
+
+    /// Get all info on a period
+    pub fn get_period(&self, id: u32) -> Result<Period> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE p.id = ?", SELECT_PERIODS)
+        )?;
+
+        return stmt.query_row([id], map_period)
+            .optional()?
+            .ok_or_else(|| Error::UnknownPeriod(id.to_string()));
+    }
+
+    /// Find a period by id, by name, or by a date it holds. Figures are taken as an id.
+    /// Periods rolled by older versions share their boundary day : the later period holds it.
+    pub fn find_period(&self, reference: &str) -> Result<Period> {
+        if let Ok(id) = reference.parse::<u32>() {
+            return self.get_period(id);
+        }
+
+        let periods = self.get_periods()?;
+        let found = match NaiveDate::parse_from_str(reference, "%Y-%m-%d") {
+            Ok(date) => periods.into_iter().rfind(|p| covers(p, date)),
+            Err(_) => periods.into_iter().find(|p| p.name.as_deref() == Some(reference)),
+        };
+
+        return found.ok_or_else(|| Error::UnknownPeriod(reference.to_string()));
+    }
+
+    /// Every period, oldest first.
+    pub fn get_periods(&self) -> Result<Vec<Period>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} ORDER BY p.start_date, p.id", SELECT_PERIODS)
+        )?;
+
+        let periods = stmt.query_map([], map_period)?;
+
+        return Ok(periods.collect::<rusqlite::Result<Vec<Period>>>()?);
+    }
+
+    /// Periods from `from` to `to` included, in chronological order.
+    /// Either bound can be left out to select every period before or after the other.
+    pub fn get_periods_between(&self, from: Option<u32>, to: Option<u32>) -> Result<Vec<Period>> {
+        let start = match from {
+            Some(id) => Some(self.get_period(id)?.start_date),
+            None => None,
+        };
+        let end = match to {
+            Some(id) => Some(self.get_period(id)?.start_date),
+            None => None,
+        };
+
+        return Ok(self.get_periods()?
+            .into_iter()
+            .filter(|p| start.is_none_or(|s| p.start_date >= s) && end.is_none_or(|e| p.start_date <= e))
+            .collect());
+    }
+
+    /// A period along with its incomes, expense lines and their transactions.
+    pub fn get_period_data(&self, period: Period) -> Result<PeriodData> {
+        let incomes = self.get_incomes(period.id)?;
+        let expenses = self.get_expenses(period.id)?;
+        let mut transactions = Vec::new();
+
+        for expense in &expenses {
+            transactions.extend(self.get_transactions(expense)?);
+        }
+
+        transactions.sort_by_key(|t| (t.date, t.id));
+
+        return Ok(PeriodData { period, incomes, expenses, transactions });
+    }
+
+    /// Close the current period yesterday and open a new one today, carrying over fixed and estimated
+    /// expense lines as well as recurring incomes. A period that started today cannot be rolled.
+    /// Returns the id of the new period.
+    pub fn roll(&self) -> Result<u32> {
+        self.record("ROLL", |changes| {
+            let period = self.get_current_period()?;
+            let today = Local::now().date_naive();
+
+            if self.get_period(period)?.start_date >= today {
+                return Err(Error::PeriodStartedToday(period));
+            }
+
+            let new_period = self.roll_over(changes, period, today.pred_opt().unwrap_or(today), today)?;
+
+            Ok(vec![period.to_string(), new_period.to_string()])
+        })?;
+
+        return self.get_current_period();
+    }
+
+    /// Roll over on every boundary of the schedule reached by `today`, the old period ending the day before
+    /// and the new one starting on the boundary, so missed periods are caught up on one by one.
+    /// A boundary whose roll-over was undone is left alone.
+    /// Returns the ids of the new periods.
+    pub fn catch_up(&self, schedule: &Schedule, today: NaiveDate) -> Result<Vec<u32>> {
+        let mut created = Vec::new();
+
+        loop {
+            let period = self.get_period(self.get_current_period()?)?;
+
+            let boundary = match schedule.next_boundary(period.start_date) {
+                Some(b) if b <= today => b,
+                _ => break,
+            };
+
+            if self.is_roll_undone(period.id, boundary)? {
+                break;
+            }
+
+            self.record("AUTO_ROLL", |changes| {
+                let end_date = boundary.pred_opt().unwrap_or(boundary);
+                let new_period = self.roll_over(changes, period.id, end_date, boundary)?;
+                created.push(new_period);
+
+                Ok(vec![period.id.to_string(), new_period.to_string(), boundary.to_string()])
+            })?;
+        }
+
+        return Ok(created);
+    }
+
+    /// Whether the automatic roll-over of a period on a boundary was undone.
+    fn is_roll_undone(&self, period: u32, boundary: NaiveDate) -> Result<bool> {
+        let count: u32 = self.conn.query_row(
+            "SELECT count(*) FROM logs l WHERE l.action = 'AUTO_ROLL' AND l.undone <> 0 AND l.arg1 = ?1 AND l.arg3 = ?2",
+            (period.to_string(), boundary.to_string()),
+            |row| row.get(0),
+        )?;
+
+        return Ok(count > 0);
+    }
+
+    /// End a period and start the next one, with the lines carried over. Returns the id of the new period.
+    fn roll_over(&self, changes: &mut Changeset, period: u32, end_date: NaiveDate, start_date: NaiveDate) -> Result<u32> {
+        check_period_dates(self.get_period(period)?.start_date, Some(end_date))?;
+        self.end_period(changes, period, end_date)?;
+        let new_period = self.insert_period(changes, start_date, None)?;
+        self.copy_fixed_and_estimates(changes, period, new_period)?;
+        self.copy_recurring_incomes(changes, period, new_period)?;
+
+        return Ok(new_period);
+    }
+
+    /// Set an end date for a period
+    fn end_period(&self, changes: &mut Changeset, id: u32, end_date: NaiveDate) -> Result<()> {
+        changes.track(&self.conn, "periods", id.into())?;
+
+        self.conn.execute(
+            "UPDATE periods SET end_date = ?1 WHERE id = ?2",
+            (end_date, id),
+        )?;
+
+        Ok(())
+    }
+
+    /// Create a period with explicit dates, carrying over the lines of the period right before it.
+    /// Without an end date, the new period becomes the ongoing one : it must start after every other period,
+    /// and the ongoing period ends the day before.
+    /// Returns the id of the new period.
+    pub fn create_period(&self, start_date: NaiveDate, end_date: Option<NaiveDate>, name: Option<&str>) -> Result<u32> {
+        let mut id = 0;
+
+        self.record("CREATE_PERIOD", |changes| {
+            check_period_dates(start_date, end_date)?;
+            check_ongoing_start(start_date, end_date)?;
+
+            if let Some(n) = name {
+                self.check_period_name(n, None)?;
+            }
+
+            let previous = self.get_periods()?
+                .into_iter()
+                .rfind(|p| p.start_date < start_date);
+
+            if let Some(ongoing) = previous.as_ref().filter(|p| p.end_date.is_none() && end_date.is_none()) {
+                self.end_period(changes, ongoing.id, start_date.pred_opt().unwrap_or(start_date))?;
+            }
+
+            self.check_no_overlap(start_date, end_date, None)?;
+            id = self.insert_period(changes, start_date, end_date)?;
+
+            self.conn.execute(
+                "UPDATE periods SET name = ?1 WHERE id = ?2",
+                (name, id),
+            )?;
+
+            if let Some(p) = previous {
+                self.copy_fixed_and_estimates(changes, p.id, id)?;
+                self.copy_recurring_incomes(changes, p.id, id)?;
+            }
+
+            Ok(vec![id.to_string(), describe_dates(start_date, end_date)])
+        })?;
+
+        return Ok(id);
+    }
+
+    /// Give a period a name, such as "October 2026", or take it away. Names are unique.
+    pub fn set_period_name(&self, period: &Period, name: Option<&str>) -> Result<()> {
+        let action = match name {
+            Some(_) => "NAME_PERIOD",
+            None => "UNNAME_PERIOD",
+        };
+
+        self.record_in(Some(period.id), action, |changes| {
+            if let Some(n) = name {
+                self.check_period_name(n, Some(period.id))?;
+            }
+
+            changes.track(&self.conn, "periods", period.id.into())?;
+
+            self.conn.execute(
+                "UPDATE periods SET name = ?1 WHERE id = ?2",
+                (name, period.id),
+            )?;
+
+            Ok(vec![period.id.to_string(), name.unwrap_or_default().to_string()])
+        })
+    }
+
+    /// Change the dates of a period. Without an end date, the period becomes ongoing again.
+    /// Periods may not overlap, and the transactions of the period must stay within its dates.
+    pub fn set_period_dates(&self, period: &Period, start_date: NaiveDate, end_date: Option<NaiveDate>) -> Result<()> {
+        self.record_in(Some(period.id), "PERIOD_DATES", |changes| {
+            self.move_period(changes, period, start_date, end_date)?;
+
+            Ok(vec![period.id.to_string(), describe_dates(start_date, end_date)])
+        })
+    }
+
+    /// Make a closed period ongoing again. Only the latest period can be.
+    pub fn reopen_period(&self, period: &Period) -> Result<()> {
+        self.record_in(Some(period.id), "REOPEN_PERIOD", |changes| {
+            self.move_period(changes, period, period.start_date, None)?;
+
+            Ok(vec![period.id.to_string()])
+        })
+    }
+
+    /// Delete a period along with its expense lines and incomes, as long as nothing was spent or received in it.
+    /// The log line goes to the current period, since the deleted one is gone.
+    pub fn delete_period(&self, period: &Period) -> Result<()> {
+        self.record("DELETE_PERIOD", |changes| {
+            let spent = self.get_expenses(period.id)?
+                .iter()
+                .map(|e| self.get_transactions(e).map(|t| t.len()))
+                .sum::<Result<usize>>()?;
+            let incomes = self.get_incomes(period.id)?;
+
+            if spent > 0 || incomes.iter().any(|i| i.received != 0) {
+                return Err(Error::PeriodNotEmpty(period.id));
+            }
+
+            for income in &incomes {
+                changes.track(&self.conn, "incomes", income.id.into())?;
+            }
+
+            for expense in self.get_expenses(period.id)? {
+                changes.track(&self.conn, "expenses", expense.id.into())?;
+            }
+
+            changes.track(&self.conn, "periods", period.id.into())?;
+
+            self.conn.execute("DELETE FROM incomes WHERE period_id = ?", [period.id])?;
+            self.conn.execute("DELETE FROM expenses WHERE period_id = ?", [period.id])?;
+            self.conn.execute("DELETE FROM periods WHERE id = ?", [period.id])?;
+
+            Ok(vec![period.id.to_string(), describe_dates(period.start_date, period.end_date)])
+        })
+    }
+
+    /// Set new dates on a period, after checking them against the other periods and its transactions.
+    fn move_period(&self, changes: &mut Changeset, period: &Period, start_date: NaiveDate, end_date: Option<NaiveDate>) -> Result<()> {
+        check_period_dates(start_date, end_date)?;
+        check_ongoing_start(start_date, end_date)?;
+        self.check_no_overlap(start_date, end_date, Some(period.id))?;
+
+        let moved = Period { start_date, end_date, ..period.clone() };
+
+        for expense in self.get_expenses(period.id)? {
+            if let Some(outside) = self.get_transactions(&expense)?.into_iter().find(|t| !covers(&moved, t.date)) {
+                return Err(Error::DateOutsidePeriod(outside.date, moved));
+            }
+        }
+
+        changes.track(&self.conn, "periods", period.id.into())?;
+
+        self.conn.execute(
+            "UPDATE periods SET start_date = ?1, end_date = ?2 WHERE id = ?3",
+            (start_date, end_date, period.id),
+        )?;
+
+        Ok(())
+    }
+
+    /// Make sure no other period bears the same name.
+    fn check_period_name(&self, name: &str, except: Option<u32>) -> Result<()> {
+        let taken = self.get_periods()?
+            .iter()
+            .any(|p| Some(p.id) != except && p.name.as_deref() == Some(name));
+
+        return match taken {
+            true => Err(Error::DuplicatePeriodName(name.to_string())),
+            false => Ok(()),
+        };
+    }
+
+    /// Create a new period
+    fn insert_period(&self, changes: &mut Changeset, start_date: NaiveDate, end_date: Option<NaiveDate>) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO periods (start_date, end_date) values (?1, ?2)",
+            (start_date, end_date),
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        changes.inserted("periods", id);
+
+        return Ok(id as u32);
+    }
+
+    /// Make sure no existing period but `except` shares a day with the given dates. Ongoing periods never end.
+    fn check_no_overlap(&self, start_date: NaiveDate, end_date: Option<NaiveDate>, except: Option<u32>) -> Result<()> {
+        let overlapping = self.get_periods()?
+            .into_iter()
+            .filter(|p| Some(p.id) != except)
+            .find(|p| end_date.is_none_or(|end| p.start_date <= end) && p.end_date.is_none_or(|end| start_date <= end));
+
+        return match overlapping {
+            Some(period) => Err(Error::OverlappingPeriod(start_date, end_date, period)),
+            None => Ok(()),
+        };
+    }
+
+    /// Copy fixed and estimated expense lines when we roll over to a new period.
+    /// Transactions stay with the old period, so the new lines start with nothing spent.
+    fn copy_fixed_and_estimates(&self, changes: &mut Changeset, old_period_id: u32, new_period_id: u32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO expenses (period_id, label, type, estimate, category_id, currency)
+                  SELECT ?1, label, type, estimate, category_id, currency
+                  FROM expenses e2
+                  WHERE e2.period_id = ?2
+                  AND e2.type in ('FIXED', 'ESTIMATED') ",
+            (new_period_id, old_period_id),
+        )?;
+
+        for expense in self.get_expenses(new_period_id)? {
+            changes.inserted("expenses", expense.id.into());
+        }
+
+        Ok(())
+    }
+
+    /// Copy recurring incomes when we roll over to a new period.
+    /// They are expected again, due at the same point of the new period.
+    fn copy_recurring_incomes(&self, changes: &mut Changeset, old_period_id: u32, new_period_id: u32) -> Result<()> {
+        let old_period = self.get_period(old_period_id)?;
+        let new_period = self.get_period(new_period_id)?;
+        let shift = new_period.start_date - old_period.start_date;
+
+        for income in self.get_incomes(old_period_id)?.iter().filter(|i| i.recurring) {
+            self.conn.execute(
+                "INSERT INTO incomes (period_id, label, value, received, expected_date, recurring, currency) values (?1, ?2, ?3, 0, ?4, 1, ?5)",
+                (new_period_id, &income.label, income.value, income.expected_date.map(|d| d + shift), &income.currency),
+            )?;
+            changes.inserted("incomes", self.conn.last_insert_rowid());
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------------------------------
+    // INCOMES
+    // ------------------------------------------------------------
+    /// Get all saved incomes
+    pub fn get_incomes(&self, period: u32) -> Result<Vec<Income>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.period_id, i.label, i.value, i.received, i.expected_date, i.recurring, i.currency FROM incomes i WHERE i.period_id = ?"
+        )?;
+
+        let incomes = stmt.query_map([period], |row| {
+            Ok(Income {
+                id: row.get(0)?,
+                period_id: row.get(1)?,
+                label: row.get(2)?,
+                value: row.get(3)?,
+                received: row.get(4)?,
+                expected_date: row.get(5)?,
+                recurring: row.get(6)?,
+                currency: currency_or_reporting(row.get(7)?),
+            })
+        })?;
+
+        return Ok(incomes.collect::<rusqlite::Result<Vec<Income>>>()?);
+    }
+
+    /// Find an income of a period by its label.
+    pub fn find_income(&self, period: u32, label: &str) -> Result<Option<Income>> {
+        return Ok(self.get_incomes(period)?
+            .into_iter()
+            .find(|inc| inc.label == label));
+    }
+
+    /// Same as `find_income`, but a missing income is an error.
+    pub fn get_income(&self, period: u32, label: &str) -> Result<Income> {
+        return self.find_income(period, label)?
+            .ok_or_else(|| Error::UnknownIncome(label.to_string()));
+    }
+
+    /// Create a new income, already received in full
+    pub fn create_income(&self, period: u32, label: &str, value: i64, currency: &str, recurring: bool) -> Result<()> {
+        self.record_in(Some(period), "ADD_INCOME", |changes| {
+            self.conn.execute(
+                "INSERT INTO incomes (period_id, label, value, received, recurring, currency) values (?1, ?2, ?3, ?3, ?4, ?5)",
+                (period, label, value, recurring, currency),
+            )?;
+            changes.inserted("incomes", self.conn.last_insert_rowid());
+
+            Ok(vec![label.to_string(), print_amount(value, currency)])
+        })
+    }
+
+    /// Create a new income that has not been received yet, optionally with the date it is due.
+    pub fn expect_income(&self, period: u32, label: &str, value: i64, currency: &str, expected_date: Option<NaiveDate>, recurring: bool) -> Result<()> {
+        self.record_in(Some(period), "EXPECT_INCOME", |changes| {
+            self.conn.execute(
+                "INSERT INTO incomes (period_id, label, value, received, expected_date, recurring, currency) values (?1, ?2, ?3, 0, ?4, ?5, ?6)",
+                (period, label, value, expected_date, recurring, currency),
+            )?;
+            changes.inserted("incomes", self.conn.last_insert_rowid());
+
+            let due = expected_date.map(|d| format!(" Due on {}.", d)).unwrap_or_default();
+            Ok(vec![label.to_string(), print_amount(value, currency), due])
+        })
+    }
+
+    /// Record that some money of an income came in.
+    pub fn receive_income(&self, income: &Income, amount: i64) -> Result<()> {
+        self.record_in(Some(income.period_id), "RECEIVE_INCOME", |changes| {
+            changes.track(&self.conn, "incomes", income.id.into())?;
+
+            self.conn.execute(
+                "UPDATE incomes SET received = received + ?1 WHERE id = ?2",
+                (amount, income.id),
+            )?;
+
+            Ok(vec![income.label.clone(), print_amount(amount, &income.currency)])
+        })
+    }
+
+    /// Set a new amount for an income. An income received in full stays received in full.
+    pub fn override_income(&self, income: &Income, new_value: i64) -> Result<()> {
+        self.record_in(Some(income.period_id), "UPDATE_INCOME", |changes| {
+            changes.track(&self.conn, "incomes", income.id.into())?;
+
+            // A fully received income is corrected as a whole, and nothing counts as received beyond the new amount.
+            let received = match income.received == income.value {
+                true => new_value,
+                false => income.received.min(new_value),
+            };
+
+            self.conn.execute(
+                "UPDATE incomes SET value = ?1, received = ?2 WHERE id = ?3",
+                (new_value, received, income.id),
+            )?;
+
+            Ok(vec![income.label.clone(), print_amount(income.value, &income.currency), print_amount(new_value, &income.currency)])
+        })
+    }
+
+    /// Make an income recur in every new period, or stop its recurrence.
+    pub fn set_income_recurring(&self, income: &Income, recurring: bool) -> Result<()> {
+        let action = if recurring { "SET_RECURRING" } else { "STOP_RECURRING" };
+
+        self.record_in(Some(income.period_id), action, |changes| {
+            changes.track(&self.conn, "incomes", income.id.into())?;
+
+            self.conn.execute(
+                "UPDATE incomes SET recurring = ?1 WHERE id = ?2",
+                (recurring, income.id),
+            )?;
+
+            Ok(vec![income.label.clone()])
+        })
+    }
+
+    /// Rename an income
+    pub fn rename_income(&self, income: &Income, new_label: &str) -> Result<()> {
+        self.record_in(Some(income.period_id), "RENAME_INCOME", |changes| {
+            changes.track(&self.conn, "incomes", income.id.into())?;
+
+            self.conn.execute(
+                "UPDATE incomes SET label = ?1 WHERE id = ?2",
+                (new_label, income.id),
+            )?;
+
+            Ok(vec![income.label.clone(), new_label.to_string()])
+        })
+    }
+
+    /// Removes an income
+    pub fn remove_income(&self, income: &Income) -> Result<()> {
+        self.record_in(Some(income.period_id), "REMOVE_INCOME", |changes| {
+            changes.track(&self.conn, "incomes", income.id.into())?;
+
+            self.conn.execute(
+                "DELETE FROM incomes WHERE id = ?",
+                [income.id],
+            )?;
+
+            Ok(vec![income.label.clone(), print_amount(income.value, &income.currency)])
+        })
+    }
+
+    // ------------------------------------------------------------
+    // EXPENSES
+    // ------------------------------------------------------------
+    /// Get all saved expenses
+    pub fn get_expenses(&self, period: u32) -> Result<Vec<Expense>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE e.period_id = ?", SELECT_EXPENSES)
+        )?;
+
+        let expenses = stmt.query_map([period], map_expense)?;
+
+        return Ok(expenses.collect::<rusqlite::Result<Vec<Expense>>>()?);
+    }
+
+    /// Get an expense line by its id, whatever its period.
+    pub fn get_expense_by_id(&self, id: u32) -> Result<Expense> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE e.id = ?", SELECT_EXPENSES)
+        )?;
+
+        return Ok(stmt.query_row([id], map_expense)?);
+    }
+
+    /// Find an expense line of a period by its label.
+    pub fn find_expense(&self, period: u32, label: &str) -> Result<Option<Expense>> {
+        return Ok(self.get_expenses(period)?
+            .into_iter()
+            .find(|exp| exp.label == label));
+    }
+
+    /// Same as `find_expense`, but a missing line is an error.
+    pub fn get_expense(&self, period: u32, label: &str) -> Result<Expense> {
+        return self.find_expense(period, label)?
+            .ok_or_else(|| Error::UnknownExpense(label.to_string()));
+    }
+
+    /// Create a new expense, with its amounts in the given currency.
+    /// A non-zero `spent` amount is recorded as a transaction dated today.
+    pub fn create_expense(&self, period: u32, label: &str, expense_type: ExpenseType, estimate: i64, spent: i64, currency: &str) -> Result<()> {
+        self.record_in(Some(period), "ADD_EXPENSE", |changes| {
+            let expense_id = self.insert_expense(changes, period, label, &expense_type, estimate, currency)?;
+
+            if spent != 0 {
+                let date = self.transaction_date(period, None)?;
+                self.insert_transaction(changes, expense_id, spent, date, None)?;
+            }
+
+            Ok(vec![label.to_string(), print_amount(estimate, currency), print_amount(spent, currency)])
+        })
+    }
+
+    /// Spend some money on the expense line with this label,
+    /// or create an unplanned line for it in the currency of the purchase if there is none yet.
+    pub fn spend(&self, period: u32, label: &str, amount: i64, currency: &str, date: Option<NaiveDate>, memo: Option<&str>) -> Result<()> {
+        if let Some(exp) = self.find_expense(period, label)? {
+            return self.increment_spending(&exp, amount, currency, date, memo);
+        }
+
+        self.record_in(Some(period), "ADD_EXPENSE", |changes| {
+            let date = self.transaction_date(period, date)?;
+            let expense_id = self.insert_expense(changes, period, label, &ExpenseType::UNPLANNED, amount, currency)?;
+            self.insert_transaction(changes, expense_id, amount, date, memo)?;
+
+            Ok(vec![label.to_string(), print_amount(amount, currency), print_amount(amount, currency)])
+        })
+    }
+
+    /// Record a purchase in the reporting currency on the line with this label,
+    /// creating an unplanned line if there is none yet. Returns whether the line was created.
+    fn spend_on(&self, changes: &mut Changeset, period: u32, label: &str, amount: i64, date: NaiveDate, memo: Option<&str>) -> Result<bool> {
+        let (expense, created) = match self.find_expense(period, label)? {
+            Some(exp) => (exp, false),
+            None => {
+                let id = self.insert_expense(changes, period, label, &ExpenseType::UNPLANNED, amount, &REPORTING_CURRENCY)?;
+                (self.get_expense_by_id(id)?, true)
+            },
+        };
+
+        self.insert_entry(changes, &expense, amount, &REPORTING_CURRENCY, date, memo, false)?;
+        return Ok(created);
+    }
+
+    /// Set a new estimate for an expense line.
+    pub fn override_estimate(&self, expense: &Expense, new_estimate: i64) -> Result<()> {
+        self.record_in(Some(expense.period_id), "UPDATE_ESTIMATE", |changes| {
+            changes.track(&self.conn, "expenses", expense.id.into())?;
+
+            self.conn.execute(
+                "UPDATE expenses SET estimate = ?1 WHERE id = ?2",
+                (new_estimate, expense.id),
+            )?;
+
+            Ok(vec![expense.label.clone(), print_amount(new_estimate, &expense.currency)])
+        })
+    }
+
+    /// Rename an expense line
+    pub fn rename_expense(&self, expense: &Expense, new_label: &str) -> Result<()> {
+        self.record_in(Some(expense.period_id), "RENAME_ESTIMATE", |changes| {
+            changes.track(&self.conn, "expenses", expense.id.into())?;
+
+            self.conn.execute(
+                "UPDATE expenses SET label = ?1 WHERE id = ?2",
+                (new_label, expense.id),
+            )?;
+
+            Ok(vec![expense.label.clone(), new_label.to_string()])
+        })
+    }
+
+    /// Removes an expense, along with its transactions
+    pub fn remove_expense(&self, expense: &Expense) -> Result<()> {
+        self.record_in(Some(expense.period_id), "REMOVE_EXPENSE", |changes| {
+            changes.track(&self.conn, "expenses", expense.id.into())?;
+
+            for transaction in self.get_transactions(expense)? {
+                changes.track(&self.conn, "transactions", transaction.id.into())?;
+            }
+
+            self.conn.execute(
+                "DELETE FROM expenses WHERE id = ?",
+                [expense.id],
+            )?;
+
+            Ok(vec![expense.label.clone()])
+        })
+    }
+
+    /// Increase the amount spent on an expense line, by an amount in the given currency.
+    /// The transaction is dated today unless a date within the period is given.
+    pub fn increment_spending(&self, expense: &Expense, amount: i64, currency: &str, date: Option<NaiveDate>, memo: Option<&str>) -> Result<()> {
+        self.record_in(Some(expense.period_id), "SPEND", |changes| {
+            let date = self.transaction_date(expense.period_id, date)?;
+            self.insert_entry(changes, expense, amount, currency, date, memo, false)?;
+
+            Ok(vec![expense.label.clone(), print_amount(amount, currency)])
+        })
+    }
+
+    /// Set the amount spent on an expense line, through a correcting transaction.
+    pub fn override_spending(&self, expense: &Expense, new_amount: i64, date: Option<NaiveDate>, memo: Option<&str>) -> Result<()> {
+        self.record_in(Some(expense.period_id), "OVERRIDE_SPENDING", |changes| {
+            let date = self.transaction_date(expense.period_id, date)?;
+            self.insert_transaction(changes, expense.id, new_amount - expense.spent, date, memo)?;
+
+            Ok(vec![expense.label.clone(), print_amount(new_amount, &expense.currency)])
+        })
+    }
+
+    /// Record money given back on an expense line, lowering the amount spent on it.
+    /// `amount` is the money given back in the given currency, as a positive figure.
+    pub fn refund(&self, expense: &Expense, amount: i64, currency: &str, date: Option<NaiveDate>, memo: Option<&str>) -> Result<()> {
+        self.record_in(Some(expense.period_id), "REFUND", |changes| {
+            let date = self.transaction_date(expense.period_id, date)?;
+            self.insert_entry(changes, expense, -amount, currency, date, memo, true)?;
+
+            Ok(vec![expense.label.clone(), print_amount(amount, currency)])
+        })
+    }
+
+    /// Increase the amount spent on an expense line to match the estimate.
+    pub fn spend_all(&self, expense: &Expense, date: Option<NaiveDate>, memo: Option<&str>) -> Result<()> {
+        return self.override_spending(expense, expense.estimate, date, memo);
+    }
+
+    fn insert_expense(&self, changes: &mut Changeset, period: u32, label: &str, expense_type: &ExpenseType, estimate: i64, currency: &str) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO expenses (period_id, label, type, estimate, currency) values (?1, ?2, ?3, ?4, ?5)",
+            (period, label, expense_type.to_string(), estimate, currency),
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        changes.inserted("expenses", id);
+
+        return Ok(id as u32);
+    }
+
+    // ------------------------------------------------------------
+    // CATEGORIES
+    // ------------------------------------------------------------
+    /// Every category, organised as a tree.
+    pub fn get_categories(&self) -> Result<CategoryTree> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.parent_id, c.name FROM categories c"
+        )?;
+
+        let categories = stmt.query_map([], |row| {
+            Ok(Category {
+                id: row.get(0)?,
+                parent_id: row.get(1)?,
+                name: row.get(2)?,
+            })
+        })?;
+
+        return Ok(CategoryTree::new(categories.collect::<rusqlite::Result<Vec<Category>>>()?));
+    }
+
+    /// File an expense line under a category path such as "Housing > Utilities > Electricity",
+    /// creating the missing categories. Lines copied on roll keep their category.
+    pub fn set_expense_category(&self, expense: &Expense, path: &str) -> Result<()> {
+        let levels = parse_path(path);
+
+        if levels.is_empty() {
+            return Err(Error::InvalidCategory(path.to_string()));
+        }
+
+        self.record_in(Some(expense.period_id), "SET_CATEGORY", |changes| {
+            let mut parent: Option<u32> = None;
+
+            for name in &levels {
+                parent = Some(self.find_or_create_category(changes, parent, name)?);
+            }
+
+            changes.track(&self.conn, "expenses", expense.id.into())?;
+
+            self.conn.execute(
+                "UPDATE expenses SET category_id = ?1 WHERE id = ?2",
+                (parent, expense.id),
+            )?;
+
+            Ok(vec![expense.label.clone(), levels.join(PATH_SEPARATOR)])
+        })
+    }
+
+    /// Take an expense line out of its category.
+    pub fn clear_expense_category(&self, expense: &Expense) -> Result<()> {
+        let Some(category_id) = expense.category_id else { return Ok(()) };
+        let path = self.get_categories()?.path(category_id);
+
+        self.record_in(Some(expense.period_id), "CLEAR_CATEGORY", |changes| {
+            changes.track(&self.conn, "expenses", expense.id.into())?;
+
+            self.conn.execute(
+                "UPDATE expenses SET category_id = NULL WHERE id = ?",
+                [expense.id],
+            )?;
+
+            Ok(vec![expense.label.clone(), path])
+        })
+    }
+
+    fn find_or_create_category(&self, changes: &mut Changeset, parent: Option<u32>, name: &str) -> Result<u32> {
+        let existing: Option<u32> = self.conn.query_row(
+            "SELECT c.id FROM categories c WHERE c.parent_id IS ?1 AND c.name = ?2",
+            (parent, name),
+            |row| row.get(0),
+        ).optional()?;
+
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+
+        self.conn.execute(
+            "INSERT INTO categories (parent_id, name) values (?1, ?2)",
+            (parent, name),
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        changes.inserted("categories", id);
+
+        return Ok(id as u32);
+    }
+
+    // ------------------------------------------------------------
+    // TRANSACTIONS
+    // ------------------------------------------------------------
+    /// Every transaction of an expense line, in chronological order.
+    pub fn get_transactions(&self, expense: &Expense) -> Result<Vec<Transaction>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE t.expense_id = ? ORDER BY t.date, t.id", SELECT_TRANSACTIONS)
+        )?;
+
+        let transactions = stmt.query_map([expense.id], map_transaction)?;
+
+        return Ok(transactions.collect::<rusqlite::Result<Vec<Transaction>>>()?);
+    }
+
+    /// Every refund on the expense lines of a period, in chronological order.
+    pub fn get_refunds(&self, period: u32) -> Result<Vec<Transaction>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} JOIN expenses e ON e.id = t.expense_id
+                      WHERE e.period_id = ? AND t.refund <> 0 ORDER BY t.date, t.id", SELECT_TRANSACTIONS)
+        )?;
+
+        let refunds = stmt.query_map([period], map_transaction)?;
+
+        return Ok(refunds.collect::<rusqlite::Result<Vec<Transaction>>>()?);
+    }
+
+    /// Get a transaction by its id.
+    pub fn get_transaction(&self, id: u32) -> Result<Transaction> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE t.id = ?", SELECT_TRANSACTIONS)
+        )?;
+
+        return stmt.query_row([id], map_transaction)
+            .optional()?
+            .ok_or(Error::UnknownTransaction(id));
+    }
+
+    /// Correct the amount, date or memo of a transaction. An empty memo clears it.
+    /// The amount of a refund is the money given back, as a positive figure.
+    pub fn amend_transaction(&self, transaction: &Transaction, amount: Option<i64>, date: Option<NaiveDate>, memo: Option<&str>) -> Result<()> {
+        let expense = self.get_expense_by_id(transaction.expense_id)?;
+
+        let amount = match (amount, transaction.refund) {
+            (Some(a), true) => -a,
+            (Some(a), false) => a,
+            (None, _) => transaction.amount,
+        };
+        let date = match date {
+            Some(d) => self.transaction_date(expense.period_id, Some(d))?,
+            None => transaction.date,
+        };
+        let memo = match memo {
+            Some("") => None,
+            Some(m) => Some(m),
+            None => transaction.memo.as_deref(),
+        };
+
+        let line_amount = self.to_line_currency(&expense, amount, &transaction.currency, date)?;
+
+        self.record_in(Some(expense.period_id), "AMEND_TRANSACTION", |changes| {
+            changes.track(&self.conn, "transactions", transaction.id.into())?;
+
+            self.conn.execute(
+                "UPDATE transactions SET amount = ?1, date = ?2, memo = ?3, line_amount = ?4 WHERE id = ?5",
+                (amount, date, memo, line_amount, transaction.id),
+            )?;
+
+            Ok(vec![
+                format!("#{} on {}", transaction.id, expense.label),
+                describe_transaction(transaction.amount, &transaction.currency, transaction.date, transaction.memo.as_deref()),
+                describe_transaction(amount, &transaction.currency, date, memo),
+            ])
+        })
+    }
+
+    /// Delete a transaction, lowering the amount spent on its expense line.
+    pub fn delete_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let expense = self.get_expense_by_id(transaction.expense_id)?;
+
+        self.record_in(Some(expense.period_id), "DELETE_TRANSACTION", |changes| {
+            changes.track(&self.conn, "transactions", transaction.id.into())?;
+
+            self.conn.execute(
+                "DELETE FROM transactions WHERE id = ?",
+                [transaction.id],
+            )?;
+
+            Ok(vec![
+                format!("#{} on {}", transaction.id, expense.label),
+                describe_transaction(transaction.amount, &transaction.currency, transaction.date, transaction.memo.as_deref()),
+            ])
+        })
+    }
+
+    /// Record a transaction in the currency of its expense line.
+    fn insert_transaction(&self, changes: &mut Changeset, expense_id: u32, amount: i64, date: NaiveDate, memo: Option<&str>) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO transactions (expense_id, amount, date, memo, created_at, currency, line_amount)
+                  SELECT ?1, ?2, ?3, ?4, CURRENT_TIMESTAMP, e.currency, ?2 FROM expenses e WHERE e.id = ?1",
+            (expense_id, amount, date, memo),
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        changes.inserted("transactions", id);
+
+        return Ok(id as u32);
+    }
+
+    /// Record a transaction in any currency, converted into the currency of its expense line
+    /// at the rate of its date.
+    #[allow(clippy::too_many_arguments)]
+    fn insert_entry(&self, changes: &mut Changeset, expense: &Expense, amount: i64, currency: &str, date: NaiveDate, memo: Option<&str>, refund: bool) -> Result<u32> {
+        let line_amount = self.to_line_currency(expense, amount, currency, date)?;
+
+        self.conn.execute(
+            "INSERT INTO transactions (expense_id, amount, date, memo, created_at, refund, currency, line_amount)
+                  values (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP, ?5, ?6, ?7)",
+            (expense.id, amount, date, memo, refund, currency, line_amount),
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        changes.inserted("transactions", id);
+
+        return Ok(id as u32);
+    }
+
+    /// Convert an amount into the currency of an expense line, at the rate of a date.
+    fn to_line_currency(&self, expense: &Expense, amount: i64, currency: &str, date: NaiveDate) -> Result<i64> {
+        if currency == expense.currency {
+            return Ok(amount);
+        }
+
+        return self.get_rates(&[currency, &expense.currency])?.convert(amount, currency, &expense.currency, date);
+    }
+
+    /// Check that a transaction date lies within the period, defaulting to today
+    /// (or the last day of the period if it is already over).
+    fn transaction_date(&self, period: u32, date: Option<NaiveDate>) -> Result<NaiveDate> {
+        let period = self.get_period(period)?;
+
+        let date = match date {
+            Some(d) => d,
+            None => {
+                let today = Local::now().date_naive();
+                period.end_date.map_or(today, |end| end.min(today))
+            }
+        };
+
+        if !period.contains(date) {
+            return Err(Error::DateOutsidePeriod(date, period));
+        }
+
+        return Ok(date);
+    }
+
+    // ------------------------------------------------------------
+    // EXCHANGE RATES
+    // ------------------------------------------------------------
+    /// Every known exchange rate of some currencies.
+    pub fn get_rates(&self, currencies: &[&str]) -> Result<Rates> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.date, r.currency, r.rate FROM rates r WHERE r.currency = ?"
+        )?;
+
+        let mut rates = Vec::new();
+
+        for currency in currencies {
+            let history = stmt.query_map([currency], map_rate)?;
+            rates.extend(history.collect::<rusqlite::Result<Vec<ExchangeRate>>>()?);
+        }
+
+        return Ok(Rates::new(rates));
+    }
+
+    /// The latest exchange rate of every currency, by currency code.
+    pub fn get_latest_rates(&self) -> Result<Vec<ExchangeRate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.date, r.currency, r.rate FROM rates r
+             WHERE r.date = (SELECT max(r2.date) FROM rates r2 WHERE r2.currency = r.currency)
+             ORDER BY r.currency"
+        )?;
+
+        let rates = stmt.query_map([], map_rate)?;
+
+        return Ok(rates.collect::<rusqlite::Result<Vec<ExchangeRate>>>()?);
+    }
+
+    /// Save exchange rates as a single undoable command, replacing those known for the same day.
+    /// Returns how many rates were added or changed.
+    pub fn import_rates(&self, rates: &[ExchangeRate], source: &str) -> Result<usize> {
+        let mut imported = 0;
+
+        self.record("IMPORT_RATES", |changes| {
+            for rate in rates {
+                let existing: Option<(i64, f64)> = self.conn.query_row(
+                    "SELECT r.id, r.rate FROM rates r WHERE r.currency = ?1 AND r.date = ?2",
+                    (&rate.currency, rate.date),
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                ).optional()?;
+
+                match existing {
+                    Some((_, known)) if known == rate.rate => continue,
+                    Some((id, _)) => {
+                        changes.track(&self.conn, "rates", id)?;
+
+                        self.conn.execute(
+                            "UPDATE rates SET rate = ?1 WHERE id = ?2",
+                            (rate.rate, id),
+                        )?;
+                    },
+                    None => {
+                        self.conn.execute(
+                            "INSERT INTO rates (date, currency, rate) values (?1, ?2, ?3)",
+                            (rate.date, &rate.currency, rate.rate),
+                        )?;
+                        changes.inserted("rates", self.conn.last_insert_rowid());
+                    },
+                }
+
+                imported += 1;
+            }
+
+            Ok(vec![source.to_string(), imported.to_string()])
+        })?;
+
+        return Ok(imported);
+    }
+
+    /// Incomes and expense lines of a period with their amounts in the reporting currency,
+    /// at the rates known at the end of the period, or today while it is ongoing.
+    /// Lines in another currency with no rate known by then are left out, their currencies returned apart.
+    pub fn in_reporting_currency(&self, period: &Period, incomes: &[Income], expenses: &[Expense]) -> Result<(Vec<Income>, Vec<Expense>, Vec<String>)> {
+        let date = period.end_date.unwrap_or_else(|| Local::now().date_naive());
+
+        let mut currencies: Vec<&str> = incomes.iter().map(|i| i.currency.as_str())
+            .chain(expenses.iter().map(|e| e.currency.as_str()))
+            .chain([REPORTING_CURRENCY.as_str()])
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+
+        let rates = self.get_rates(&currencies)?;
+        let known = |currency: &str| currency == *REPORTING_CURRENCY
+            || (rates.rate(currency, date).is_ok() && rates.rate(&REPORTING_CURRENCY, date).is_ok());
+
+        let incomes: Vec<Income> = incomes.iter().filter(|i| known(&i.currency)).cloned().collect();
+        let expenses: Vec<Expense> = expenses.iter().filter(|e| known(&e.currency)).cloned().collect();
+        let missing = currencies.into_iter()
+            .filter(|c| !known(c))
+            .map(|c| c.to_string())
+            .collect();
+
+        return Ok((
+            rates.convert_incomes(&incomes, &REPORTING_CURRENCY, date)?,
+            rates.convert_expenses(&expenses, &REPORTING_CURRENCY, date)?,
+            missing,
+        ));
+    }
+
+    // ------------------------------------------------------------
+    // IMPORTS
+    // ------------------------------------------------------------
+    /// Import bank statement entries into a period, as a single undoable command.
+    /// Money going out is spent on the matching expense line (see `spend`), money coming in
+    /// is received on the matching income. Amounts are in the reporting currency, and converted for lines
+    /// kept in another one. Entries imported before, or dated outside of the period, are skipped.
+    pub fn import_entries(&self, period: u32, entries: &[StatementEntry], rules: &LabelRules, source: &str) -> Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        let target = self.get_period(period)?;
+
+        self.record_in(Some(period), "IMPORT", |changes| {
+            let mut imported = 0;
+
+            for entry in entries {
+                if entry.amount == 0 {
+                    continue;
+                }
+
+                if !target.contains(entry.date) {
+                    summary.outside_period += 1;
+                    continue;
+                }
+
+                if !self.claim_import_reference(changes, &entry.reference)? {
+                    summary.duplicates += 1;
+                    continue;
+                }
+
+                let label = rules.label_for(&entry.description).unwrap_or(&entry.description);
+
+                if entry.amount < 0 {
+                    let created = self.spend_on(changes, period, label, -entry.amount, entry.date, Some(&entry.description))?;
+                    summary.count_expense(label, -entry.amount, created);
+                }
+
+                else {
+                    let created = self.receive_on(changes, period, label, entry.amount, entry.date)?;
+                    summary.count_income(label, entry.amount, created);
+                }
+
+                imported += 1;
+            }
+
+            Ok(vec![source.to_string(), imported.to_string()])
+        })?;
+
+        return Ok(summary);
+    }
+
+    /// Import the periods of a legacy worksheet, with their expense lines and incomes,
+    /// as a single undoable command. Amounts spent are dated at the start of their period.
+    /// Returns the ids of the new periods.
+    pub fn import_worksheet(&self, periods: &[WorksheetPeriod], source: &str) -> Result<Vec<u32>> {
+        let mut ids = Vec::new();
+
+        self.record("IMPORT_WORKSHEET", |changes| {
+            for period in periods {
+                self.check_no_overlap(period.start_date, Some(period.end_date), None)?;
+                let period_id = self.insert_period(changes, period.start_date, Some(period.end_date))?;
+
+                for line in &period.lines {
+                    match &line.kind {
+                        LineKind::Income => {
+                            let value = line.estimate.or(line.spent).unwrap_or(0);
+
+                            self.conn.execute(
+                                "INSERT INTO incomes (period_id, label, value, received, currency) values (?1, ?2, ?3, ?4, ?5)",
+                                (period_id, &line.label, value, line.spent.unwrap_or(value), &*REPORTING_CURRENCY),
+                            )?;
+                            changes.inserted("incomes", self.conn.last_insert_rowid());
+                        },
+                        LineKind::Expense(expense_type) => {
+                            let spent = line.spent.unwrap_or(0);
+                            let estimate = match expense_type {
+                                ExpenseType::UNPLANNED => line.estimate.unwrap_or(spent),
+                                _ => line.estimate.unwrap_or(0),
+                            };
+
+                            let expense_id = self.insert_expense(changes, period_id, &line.label, expense_type, estimate, &REPORTING_CURRENCY)?;
+
+                            if spent != 0 {
+                                self.insert_transaction(changes, expense_id, spent, period.start_date, None)?;
+                            }
+                        },
+                    }
+                }
+
+                ids.push(period_id);
+            }
+
+            Ok(vec![source.to_string(), ids.len().to_string()])
+        })?;
+
+        return Ok(ids);
+    }
+
+    /// Import the expense and income postings of a journal, as a single undoable command.
+    /// Each posting goes to the period holding its date. Dates outside of every period get a new closed
+    /// period for their month, shortened so as not to overlap the existing ones.
+    /// Expense postings are spent on the line labelled after the account (or the matching rule), created
+    /// with the type of the account if needed, with its estimate matching what the import spent on it.
+    /// Income postings are received on the matching income. Postings imported before are skipped.
+    pub fn import_journal(&self, postings: &[JournalPosting], rules: &LabelRules, source: &str) -> Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        let mut periods = self.get_periods()?;
+        let mut created_lines = HashSet::new();
+
+        self.record("IMPORT", |changes| {
+            let mut imported = 0;
+
+            for posting in postings {
+                if posting.amount == 0 {
+                    continue;
+                }
+
+                if !self.claim_import_reference(changes, &posting.reference)? {
+                    summary.duplicates += 1;
+                    continue;
+                }
+
+                let period = match periods.iter().find(|p| covers(p, posting.date)) {
+                    Some(p) => p.id,
+                    None => {
+                        let (start_date, end_date) = free_month_around(&periods, posting.date);
+                        let id = self.insert_period(changes, start_date, Some(end_date))?;
+
+                        periods.push(Period { id, start_date, end_date: Some(end_date), name: None });
+                        summary.created_periods.push(id);
+                        id
+                    }
+                };
+
+                let default_label = account_label(&posting.account);
+                let label = rules.label_for(&posting.account).unwrap_or(&default_label);
+
+                match &posting.kind {
+                    PostingKind::Expense(expense_type) => {
+                        let created = match self.find_expense(period, label)? {
+                            Some(exp) => {
+                                if created_lines.contains(&exp.id) {
+                                    self.conn.execute(
+                                        "UPDATE expenses SET estimate = estimate + ?1 WHERE id = ?2",
+                                        (posting.amount, exp.id),
+                                    )?;
+                                }
+
+                                self.insert_entry(changes, &exp, posting.amount, &REPORTING_CURRENCY, posting.date, Some(&posting.description), false)?;
+                                false
+                            },
+                            None => {
+                                let id = self.insert_expense(changes, period, label, expense_type, posting.amount, &REPORTING_CURRENCY)?;
+                                self.insert_transaction(changes, id, posting.amount, posting.date, Some(&posting.description))?;
+                                created_lines.insert(id);
+                                true
+                            }
+                        };
+
+                        summary.count_expense(label, posting.amount, created);
+                    },
+                    PostingKind::Income => {
+                        let created = self.receive_on(changes, period, label, posting.amount, posting.date)?;
+                        summary.count_income(label, posting.amount, created);
+                    },
+                }
+
+                imported += 1;
+            }
+
+            Ok(vec![source.to_string(), imported.to_string()])
+        })?;
+
+        return Ok(summary);
+    }
+
+    /// Remember an imported entry. Returns false if it was already imported.
+    fn claim_import_reference(&self, changes: &mut Changeset, reference: &str) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO imports (reference, created_at) values (?1, CURRENT_TIMESTAMP)",
+            [reference],
+        )?;
+
+        if inserted == 0 {
+            return Ok(false);
+        }
+
+        changes.inserted("imports", self.conn.last_insert_rowid());
+        return Ok(true);
+    }
+
+    /// Receive money in the reporting currency on the income with this label, converted at the rate of its date,
+    /// creating a received income if there is none yet. Returns whether the income was created.
+    fn receive_on(&self, changes: &mut Changeset, period: u32, label: &str, amount: i64, date: NaiveDate) -> Result<bool> {
+        match self.find_income(period, label)? {
+            Some(income) => {
+                let amount = match income.currency == *REPORTING_CURRENCY {
+                    true => amount,
+                    false => self.get_rates(&[&REPORTING_CURRENCY, &income.currency])?.convert(amount, &REPORTING_CURRENCY, &income.currency, date)?,
+                };
+
+                changes.track(&self.conn, "incomes", income.id.into())?;
+
+                self.conn.execute(
+                    "UPDATE incomes SET received = received + ?1 WHERE id = ?2",
+                    (amount, income.id),
+                )?;
+
+                return Ok(false);
+            },
+            None => {
+                self.conn.execute(
+                    "INSERT INTO incomes (period_id, label, value, received, currency) values (?1, ?2, ?3, ?3, ?4)",
+                    (period, label, amount, &*REPORTING_CURRENCY),
+                )?;
+                changes.inserted("incomes", self.conn.last_insert_rowid());
+
+                return Ok(true);
+            }
+        }
+    }
+
+    // ------------------------------------------------------------
+    // LOGS
+    // ------------------------------------------------------------
+    /// Every log line, most recent first.
+    pub fn get_all_logs(&self) -> Result<Vec<Log>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} ORDER BY l.id desc", SELECT_LOGS)
+        )?;
+
+        let logs = stmt.query_map([], map_log)?;
+
+        return Ok(logs.collect::<rusqlite::Result<Vec<Log>>>()?);
+    }
+
+    /// Log lines of the current period, most recent first.
+    pub fn get_current_logs(&self) -> Result<Vec<Log>> {
+        return self.get_period_logs(self.get_current_period()?);
+    }
+
+    /// Log lines of the commands that changed a period, most recent first.
+    pub fn get_period_logs(&self, period_id: u32) -> Result<Vec<Log>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE l.period_id = ? ORDER BY l.id desc", SELECT_LOGS)
+        )?;
+
+        let logs = stmt.query_map([period_id], map_log)?;
+
+        return Ok(logs.collect::<rusqlite::Result<Vec<Log>>>()?);
+    }
+
+    /// Run a command atomically, and log it along with every row it changed.
+    /// The command returns the parameters of its log line.
+    fn record<F>(&self, action: &str, command: F) -> Result<()>
+    where F: FnOnce(&mut Changeset) -> Result<Vec<String>> {
+        return self.record_in(None, action, command);
+    }
+
+    /// Same as `record`, the log line being filed under the period the command changed.
+    /// Without one, it goes to the period that is current once the command is done.
+    fn record_in<F>(&self, period: Option<u32>, action: &str, command: F) -> Result<()>
+    where F: FnOnce(&mut Changeset) -> Result<Vec<String>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changes = Changeset::default();
+
+        let params = command(&mut changes)?;
+        self.create_log(period, action, &params, &mut changes)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Create a new line of log. A non-empty changeset makes it undoable,
+    /// and discards the commands that were undone before it.
+    fn create_log(&self, period: Option<u32>, action: &str, params: &[String], changes: &mut Changeset) -> Result<()> {
+        let period_id = match period {
+            Some(id) => id,
+            None => self.get_current_period()?,
+        };
+
+        let serialized = match changes.is_empty() {
+            true => None,
+            false => {
+                changes.seal(&self.conn)?;
+
+                self.conn.execute(
+                    "UPDATE logs SET undone = ?1 WHERE undone = ?2",
+                    (UndoState::Discarded, UndoState::Undone),
+                )?;
+
+                Some(serde_json::to_string(changes)?)
+            }
+        };
+
+        self.conn.execute(
+            "INSERT INTO logs (period_id, timer, action, arg1, arg2, arg3, changes) values (?1, CURRENT_TIMESTAMP, ?2, ?3, ?4, ?5, ?6)",
+            (period_id, action, params.first(), params.get(1), params.get(2), serialized),
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------------------------------
+    // UNDO
+    // ------------------------------------------------------------
+    /// Undo the last `count` commands, most recent first. Returns the log lines of the undone commands.
+    pub fn undo(&self, count: u32) -> Result<Vec<Log>> {
+        let mut undone = Vec::new();
+
+        for _ in 0..count {
+            let entry = match self.find_history("l.undone = 0 ORDER BY l.id desc")? {
+                Some(e) => e,
+                None => break,
+            };
+
+            self.record("UNDO", |_| {
+                let changes = self.get_changeset(entry.id)?;
+
+                if !changes.is_applied(&self.conn)? {
+                    return Err(Error::HistoryConflict(entry.id));
+                }
+
+                changes.revert(&self.conn)?;
+                self.set_undo_state(entry.id, UndoState::Undone)?;
+
+                Ok(vec![entry.id.to_string(), entry.description()])
+            })?;
+
+            undone.push(entry);
+        }
+
+        return Ok(undone);
+    }
+
+    /// Redo the last undone command. Returns its log line, if any.
+    pub fn redo(&self) -> Result<Option<Log>> {
+        let entry = match self.find_history("l.undone = 1 ORDER BY l.id asc")? {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        self.record("REDO", |_| {
+            let changes = self.get_changeset(entry.id)?;
+
+            if !changes.is_reverted(&self.conn)? {
+                return Err(Error::HistoryConflict(entry.id));
+            }
+
+            changes.apply(&self.conn)?;
+            self.set_undo_state(entry.id, UndoState::Active)?;
+
+            Ok(vec![entry.id.to_string(), entry.description()])
+        })?;
+
+        return Ok(Some(entry));
+    }
+
+    /// First undoable log line matching the condition.
+    fn find_history(&self, condition: &str) -> Result<Option<Log>> {
+        let mut stmt = self.conn.prepare(
+            &format!("{} WHERE l.changes IS NOT NULL AND {} LIMIT 1", SELECT_LOGS, condition)
+        )?;
+
+        return Ok(stmt.query_row([], map_log).optional()?);
+    }
+
+    fn get_changeset(&self, log_id: u32) -> Result<Changeset> {
+        let raw: String = self.conn.query_row(
+            "SELECT l.changes FROM logs l WHERE l.id = ?",
+            [log_id],
+            |row| row.get(0),
+        )?;
+
+        return Ok(serde_json::from_str(&raw)?);
+    }
+
+    fn set_undo_state(&self, log_id: u32, state: UndoState) -> Result<()> {
+        self.conn.execute(
+            "UPDATE logs SET undone = ?1 WHERE id = ?2",
+            (state, log_id),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A period may not end before it starts.
+fn check_period_dates(start_date: NaiveDate, end_date: Option<NaiveDate>) -> Result<()> {
+    return match end_date {
+        Some(end) if end < start_date => Err(Error::InvalidPeriodDates(start_date, end)),
+        _ => Ok(()),
+    };
+}
+
+/// An ongoing period holds today, so it may not start later.
+fn check_ongoing_start(start_date: NaiveDate, end_date: Option<NaiveDate>) -> Result<()> {
+    return match end_date {
+        None if start_date > Local::now().date_naive() => Err(Error::FutureOngoingPeriod(start_date)),
+        _ => Ok(()),
+    };
+}
+
+fn describe_dates(start_date: NaiveDate, end_date: Option<NaiveDate>) -> String {
+    return format!("{} -> {}", start_date, end_date.map(|d| d.to_string()).unwrap_or("ongoing".to_string()));
+}
+
+/// Whether a period holds a date. Ongoing periods hold every date from their start on : unlike
+/// `Period::contains`, which stops them today so nothing is recorded in the future, this finds the period
+/// a later date belongs to, since no other period can be created over the ongoing one.
+fn covers(period: &Period, date: NaiveDate) -> bool {
+    return period.start_date <= date && period.end_date.is_none_or(|end| date <= end);
+}
+
+/// The calendar month of a date no period holds, shortened so as not to overlap the periods around it.
+fn free_month_around(periods: &[Period], date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let first = date.with_day(1).unwrap_or(date);
+    let last = (first + Months::new(1)).pred_opt().unwrap_or(date);
+
+    let start_date = periods.iter()
+        .filter_map(|p| p.end_date.filter(|end| *end < date))
+        .filter_map(|end| end.succ_opt())
+        .fold(first, |start, after| start.max(after));
+
+    let end_date = periods.iter()
+        .filter(|p| p.start_date > date)
+        .filter_map(|p| p.start_date.pred_opt())
+        .fold(last, |end, before| end.min(before));
+
+    return (start_date, end_date);
+}
+
+const SELECT_PERIODS: &str =
+    "SELECT p.id, p.start_date, p.end_date, p.name FROM periods p";
+
+fn map_period(row: &rusqlite::Row) -> rusqlite::Result<Period> {
+    Ok(Period {
+        id: row.get(0)?,
+        start_date: row.get(1)?,
+        end_date: row.get(2)?,
+        name: row.get(3)?,
+    })
+}
+
+const SELECT_EXPENSES: &str =
+    "SELECT e.id, e.period_id, e.label, e.type, e.estimate,
+            ifnull((SELECT sum(t.line_amount) FROM transactions t WHERE t.expense_id = e.id), 0),
+            e.category_id,
+            ifnull((SELECT -sum(t.line_amount) FROM transactions t WHERE t.expense_id = e.id AND t.refund <> 0), 0),
+            e.currency
+     FROM expenses e";
+
+fn map_expense(row: &rusqlite::Row) -> rusqlite::Result<Expense> {
+    Ok(Expense {
+        id: row.get(0)?,
+        period_id: row.get(1)?,
+        label: row.get(2)?,
+        expense_type: row.get(3)?,
+        estimate: row.get(4)?, // in cents.
+        spent: row.get(5)?, // in cents.
+        category_id: row.get(6)?,
+        refunded: row.get(7)?, // in cents.
+        currency: currency_or_reporting(row.get(8)?),
+    })
+}
+
+/// Short description of a transaction's values for the audit log.
+fn describe_transaction(amount: i64, currency: &str, date: NaiveDate, memo: Option<&str>) -> String {
+    return match memo {
+        Some(m) => format!("{} on {} ({})", print_amount(amount, currency), date, m),
+        None => format!("{} on {}", print_amount(amount, currency), date),
+    };
+}
+
+const SELECT_TRANSACTIONS: &str =
+    "SELECT t.id, t.expense_id, t.amount, t.date, t.memo, t.created_at, t.refund <> 0, t.currency, t.line_amount FROM transactions t";
+
+fn map_transaction(row: &rusqlite::Row) -> rusqlite::Result<Transaction> {
+    Ok(Transaction {
+        id: row.get(0)?,
+        expense_id: row.get(1)?,
+        amount: row.get(2)?,
+        date: row.get(3)?,
+        memo: row.get(4)?,
+        created_at: row.get(5)?,
+        refund: row.get(6)?,
+        currency: currency_or_reporting(row.get(7)?),
+        line_amount: row.get::<_, Option<i64>>(8)?.unwrap_or(row.get(2)?),
+    })
+}
+
+/// Rows restored from the history of a database older than currencies may lack one until the ledger opens again.
+fn currency_or_reporting(currency: Option<String>) -> String {
+    return currency.unwrap_or_else(|| REPORTING_CURRENCY.clone());
+}
+
+fn map_rate(row: &rusqlite::Row) -> rusqlite::Result<ExchangeRate> {
+    Ok(ExchangeRate {
+        date: row.get(0)?,
+        currency: row.get(1)?,
+        rate: row.get(2)?,
+    })
+}
+
+const SELECT_LOGS: &str =
+    "SELECT l.id, l.timer, l.action, l.arg1, l.arg2, l.arg3, l.undone <> 0, l.period_id FROM logs l";
+
+fn map_log(row: &rusqlite::Row) -> rusqlite::Result<Log> {
+    Ok(Log {
+        id: row.get(0)?,
+        timer: row.get(1)?,
+        action: row.get(2)?,
+        arg1: row.get(3)?,
+        arg2: row.get(4)?,
+        arg3: row.get(5)?,
+        undone: row.get(6)?,
+        period_id: row.get(7)?,
+    })
+}
+
+/// Value of `logs.undone`.
+#[derive(Clone, Copy)]
+enum UndoState {
+    Active = 0,
+    Undone = 1,
+    /// Undone, then superseded by a new command : it can no longer be redone.
+    Discarded = 2,
+}
+
+impl ToSql for UndoState {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        return Ok(ToSqlOutput::from(*self as i64));
+    }
+}
+
+// ------------------------------------------------------------
+// BALANCE
+// ------------------------------------------------------------
+/// Get the current balance (all incomes received, minus all expenses spent)
+pub fn get_balance(incomes: &[Income], expenses: &[Expense]) -> i64 {
+    let income_sum: i64 = incomes.iter()
+                        .map(|x| x.received)
+                        .sum();
+
+    let expense_sum: i64 = expenses.iter()
+                        .map(|x| x.spent)
+                        .sum();
+
+    return income_sum - expense_sum;
+}
+
+/// Get the balance expected at the end of the period (all incomes received or expected, minus all expenses estimated)
+pub fn get_end_of_period_estimate(incomes: &[Income], expenses: &[Expense]) -> i64 {
+    let income_sum: i64 = incomes.iter()
+                        .map(|x| x.received + x.pending())
+                        .sum();
+
+    let expense_sum: i64 = expenses.iter()
+                        .map(|x| x.estimate)
+                        .sum();
+
+    return income_sum - expense_sum;
+}
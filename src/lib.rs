@@ -0,0 +1,26 @@
+//! Ebenezer - a lightweight personal budget library.
+//!
+//! The [`Ledger`] owns the SQLite connection and exposes every operation of the CLI
+//! as a typed method, so the budget can be scripted from other Rust tools.
+#![allow(clippy::needless_return)]
+
+pub mod categories;
+pub mod currencies;
+pub mod error;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod ledger;
+pub mod migrations;
+pub mod report;
+pub mod schedule;
+pub mod settings;
+pub mod structs;
+pub mod utils;
+
+pub use crate::error::{Error, Result};
+pub use crate::ledger::Ledger;
+pub use crate::settings::get_currency;
+
+#[macro_use]
+extern crate lazy_static;
@@ -1,27 +1,27 @@
-use std::io::Error;
-use std::{fmt, fs};
-use config::Config;
-use rusqlite::{Connection, Result};
-use clap::{Parser, Subcommand};
-use crate::structs::{Log, Period, Income, Expense, ExpenseType};
-use crate::utils::{parse_into_cents, print_in_currency};
-use homedir::get_my_home;
-
-pub mod structs;
-pub mod utils;
-
-#[macro_use]
-extern crate lazy_static;
-
-// ------------------------------------------------------------
-// CONFIG
-// ------------------------------------------------------------
-lazy_static!{
-    pub static ref CONFIG: Config = Config::builder()
-        .add_source(config::Environment::with_prefix("EBENEZER").separator("_"))
-        .build()
-        .unwrap();
-}
+#![allow(clippy::needless_return)]
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use chrono::{Local, NaiveDate};
+use serde::Serialize;
+use serde_json::json;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ebenezer::{Error, Ledger, Result};
+use ebenezer::categories::CategoryTree;
+use ebenezer::currencies::parse_code;
+use ebenezer::export::{self, PeriodData};
+use ebenezer::import::{camt, csv, journal, mt940, ofx, qif, rates, ImportSummary, LabelRules};
+use ebenezer::import::csv::CsvMapping;
+use ebenezer::import::worksheet::{self, LineKind, WorksheetPeriod};
+use ebenezer::ledger::{get_balance, get_end_of_period_estimate};
+use ebenezer::migrations::SCHEMA_VERSION;
+use ebenezer::report::{Balance, CategoryReport, ExpenseReport, LogReport, PeriodReport};
+use ebenezer::settings::{get_dbfile, get_money_format, get_period_schedule, REPORTING_CURRENCY};
+use ebenezer::structs::{Log, Income, Expense, ExpenseType, Period, Transaction};
+use ebenezer::utils::{parse_in_currency, print_in_currency};
 
 // ------------------------------------------------------------
 // CLI
@@ -33,17 +33,42 @@ lazy_static!{
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Period to work on, by id, name or a date it holds (YYYY-MM-DD). Defaults to the current period.
+    #[arg(long, global = true)]
+    period: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Yaml,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Show the current DB path
+    /// Show the current DB path and schema version
     Database,
 
     /// List incomes and expenses
-    List { id: Option<u32> },
+    List {
+        id: Option<u32>,
+
+        /// Group expense lines by category, with subtotals
+        #[arg(long)]
+        by_category: bool,
 
-    /// List every transaction of the current period for auditing purposes
+        /// Only show category subtotals, rolled up at this depth (1 for top-level categories). Implies --by-category.
+        #[arg(long)]
+        depth: Option<usize>,
+    },
+
+    /// List every transaction of the current period (or --period) for auditing purposes
     Logs,
 
     /// List every transaction for auditing purposes
@@ -52,200 +77,773 @@ enum Commands {
     /// Switch to a new period
     Roll,
 
-    /// Display the current period
-    Period,
+    /// Display the current period, or manage periods
+    Period {
+        #[command(subcommand)]
+        action: Option<PeriodAction>,
+    },
 
     /// Remove an expense line
     Remove { label: String },
 
     /// Spend some money on an expense line. If amount is omitted, the whole expense is spent.
-    Spend { label: String, amount: Option<String> },
+    Spend {
+        label: String,
+
+        #[arg(allow_hyphen_values = true)]
+        amount: Option<String>,
+
+        /// Currency of the purchase (ISO 4217 code), converted into the one of the line. Defaults to the line's.
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Date of the purchase (YYYY-MM-DD), within the current period. Defaults to today.
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Free-text note attached to the purchase
+        #[arg(long)]
+        memo: Option<String>,
+    },
+
+    /// Record money given back on an expense line, lowering the amount spent on it
+    Refund {
+        label: String,
+
+        /// Money given back
+        amount: String,
+
+        /// Currency of the refund (ISO 4217 code), converted into the one of the line. Defaults to the line's.
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Date of the refund (YYYY-MM-DD), within the current period. Defaults to today.
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Free-text note attached to the refund
+        #[arg(long)]
+        memo: Option<String>,
+    },
+
+    /// List the transactions of an expense line
+    Transactions { label: String },
+
+    /// Correct the amount, date or memo of a transaction. An empty memo clears it. The amount of a refund is the money given back.
+    Amend {
+        id: u32,
+
+        #[arg(long, allow_hyphen_values = true)]
+        amount: Option<String>,
+
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        #[arg(long)]
+        memo: Option<String>,
+    },
+
+    /// Delete a transaction
+    Delete { id: u32 },
 
     /// Create a new constant expense line
-    Fixed { label: String, amount: String },
+    Fixed {
+        label: String,
+
+        #[arg(allow_hyphen_values = true)]
+        amount: String,
+
+        /// Currency of the line (ISO 4217 code). Defaults to the reporting currency.
+        #[arg(long)]
+        currency: Option<String>,
+    },
     
     /// Create a new estimated expense line
-    Estimate { label: String, amount: String },
+    Estimate {
+        label: String,
+
+        #[arg(allow_hyphen_values = true)]
+        amount: String,
+
+        /// Currency of the line (ISO 4217 code). Defaults to the reporting currency.
+        #[arg(long)]
+        currency: Option<String>,
+    },
+
+    /// Create a new income, or update its amount
+    Income {
+        label: String,
+
+        #[arg(allow_hyphen_values = true)]
+        amount: String,
+
+        /// The money has not come in yet : it only counts towards the end of period estimate
+        #[arg(long)]
+        expected: bool,
+
+        /// Date the income is due (YYYY-MM-DD). Implies --expected.
+        #[arg(long)]
+        on: Option<NaiveDate>,
+
+        /// Expect this income again in every new period
+        #[arg(long)]
+        recurring: bool,
+
+        /// Currency of the income (ISO 4217 code). Defaults to the reporting currency.
+        #[arg(long)]
+        currency: Option<String>,
+    },
+
+    /// Make an income recur in every new period
+    Recurring {
+        label: String,
+
+        /// Stop the recurrence instead
+        #[arg(long)]
+        off: bool,
+    },
+
+    /// Mark an expected income as received. If amount is omitted, the whole remainder is received.
+    Receive {
+        label: String,
+
+        #[arg(allow_hyphen_values = true)]
+        amount: Option<String>,
+    },
+
+    /// List incomes
+    Incomes,
+
+    /// Change the label of an income
+    RenameIncome { old: String, new: String },
+
+    /// Remove an income
+    RemoveIncome { label: String },
 
-    /// Create a new constant expense line
-    Income { label: String, amount: String },
-    
     /// Change the label of an expense line
     Rename { old: String, new: String },
+
+    /// File an expense line under a category such as "Housing > Utilities". If path is omitted, the line is taken out of its category.
+    Categorize { label: String, path: Option<String> },
+
+    /// List categories
+    Categories,
+
+    /// List the latest exchange rates
+    Rates,
+
+    /// Import a bank statement into the current period, a legacy worksheet or exchange rates
+    Import {
+        #[command(subcommand)]
+        format: ImportFormat,
+    },
+
+    /// Export periods to a file
+    Export {
+        #[command(subcommand)]
+        format: ExportFormat,
+    },
+
+    /// Undo the last commands (one by default)
+    Undo { count: Option<u32> },
+
+    /// Redo the last undone command
+    Redo,
 }
 
-// ------------------------------------------------------------
-// CORE
-// ------------------------------------------------------------
-fn prepare_database_dir() -> Result<(), Error> {
-    let path = get_my_home().unwrap().unwrap().as_path()
-    .join("ebenezer".to_string());
+#[derive(Subcommand)]
+enum PeriodAction {
+    /// List every period
+    List,
+
+    /// Create a period, carrying over the lines of the period before it. Without an end date, it ends the ongoing period the day before.
+    New {
+        /// First day of the period (YYYY-MM-DD)
+        #[arg(long)]
+        start: NaiveDate,
+
+        /// Last day of the period (YYYY-MM-DD). Defaults to an ongoing period.
+        #[arg(long)]
+        end: Option<NaiveDate>,
+
+        /// Name of the period, such as "October 2026"
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Name a period, such as "October 2026". If name is omitted, the period loses its name.
+    Name { id: u32, name: Option<String> },
+
+    /// Change the first or last day of a period
+    Dates {
+        id: u32,
+
+        /// First day of the period (YYYY-MM-DD)
+        #[arg(long)]
+        start: Option<NaiveDate>,
+
+        /// Last day of the period (YYYY-MM-DD)
+        #[arg(long)]
+        end: Option<NaiveDate>,
+    },
+
+    /// Make a closed period ongoing again. Only the latest period can be reopened.
+    Reopen { id: u32 },
+
+    /// Delete a period with nothing spent or received, along with its expense lines and incomes
+    Delete { id: u32 },
+}
 
-    if !path.exists() || !path.is_dir() {
-        return fs::create_dir(path);
-    }
+#[derive(Subcommand)]
+enum ImportFormat {
+    /// CSV statement with one entry per row
+    Csv {
+        file: PathBuf,
 
-    else {
-        return Ok(());
-    }
+        /// Date column, by header name or 1-based position
+        #[arg(long, default_value = "date")]
+        date: String,
+
+        /// Amount column, by header name or 1-based position
+        #[arg(long, default_value = "amount")]
+        amount: String,
+
+        /// Description column, by header name or 1-based position
+        #[arg(long, default_value = "description")]
+        description: String,
+
+        /// Format of the dates, e.g. %d/%m/%Y
+        #[arg(long, default_value = "%Y-%m-%d")]
+        date_format: String,
+
+        #[arg(long, default_value_t = ',')]
+        decimal_separator: char,
+
+        #[arg(long, default_value_t = ';')]
+        delimiter: char,
+
+        /// The first row holds data, not column names
+        #[arg(long)]
+        no_header: bool,
+
+        /// Amounts are positive for money going out
+        #[arg(long)]
+        inverted: bool,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// OFX or QFX statement, as downloaded from most banks
+    Ofx {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// ISO 20022 camt.053 XML statement
+    Camt {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// SWIFT MT940 statement
+    Mt940 {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// Ledger or hledger journal, spread over the periods holding its dates
+    Journal {
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+
+    /// Legacy label / estimate / spent worksheet (.xlsx, .xls, .ods or .csv), one period per sheet
+    Worksheet {
+        file: PathBuf,
+
+        /// Start date of the first period, for sheets not named after their month
+        #[arg(long)]
+        start: Option<NaiveDate>,
+    },
+
+    /// ECB-style CSV file of exchange rates against the euro, such as eurofxref-hist.csv
+    Rates { file: PathBuf },
+
+    /// QIF statement
+    Qif {
+        file: PathBuf,
+
+        /// Format of the dates, e.g. %m/%d/%Y for US exports
+        #[arg(long, default_value = "%d/%m/%Y")]
+        date_format: String,
+
+        #[command(flatten)]
+        options: ImportOptions,
+    },
 }
 
-/// Returns the path to the SQLite DB file, either from the configuration or a reasonable default.
-fn get_dbfile() -> String {
-    prepare_database_dir().expect("Unable to create database directory");
-
-    return match CONFIG.get::<String>("dbfile") {
-        Ok(r) => r,
-        _ => {
-           return get_my_home().unwrap().unwrap().as_path()
-            .join("ebenezer".to_string())
-            .join("ebenezer.db3".to_string())
-            .to_str()
-            .unwrap_or("./ebenezer.db3")
-            .to_string();
-        }
-    }
+#[derive(Subcommand)]
+enum ExportFormat {
+    /// CSV file with one row per income and expense line, amounts in plain decimal
+    Csv {
+        #[command(flatten)]
+        selection: PeriodSelection,
+
+        #[command(flatten)]
+        output: ExportOutput,
+    },
+
+    /// Beancount ledger
+    Beancount {
+        #[command(flatten)]
+        selection: PeriodSelection,
+
+        #[command(flatten)]
+        output: ExportOutput,
+    },
+
+    /// Ledger journal, readable by ledger and hledger
+    Journal {
+        #[command(flatten)]
+        selection: PeriodSelection,
+
+        #[command(flatten)]
+        output: ExportOutput,
+    },
 }
 
-/// Returns the currency symbol to use, either from the configuration or a reasonable default.
-pub fn get_currency() -> String {
-    return match CONFIG.get::<String>("currency") {
-        Ok(r) => r,
-        _ => "€".to_string()
-    }
+/// Periods to export : the one given with --period, or the current one, by default.
+#[derive(Args)]
+struct PeriodSelection {
+    /// Export the periods from this one onwards
+    #[arg(long, conflicts_with = "period")]
+    from: Option<u32>,
+
+    /// Export the periods up to this one
+    #[arg(long, conflicts_with = "period")]
+    to: Option<u32>,
+
+    /// Export every period
+    #[arg(long, conflicts_with_all = ["from", "to", "period"])]
+    all: bool,
+}
+
+#[derive(Args)]
+struct ExportOutput {
+    /// File to write, standard output by default
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ImportOptions {
+    /// File of 'pattern = label' lines, mapping entry descriptions (journal accounts) to expense lines and incomes
+    #[arg(long)]
+    rules: Option<PathBuf>,
 }
 
+// ------------------------------------------------------------
+// CORE
+// ------------------------------------------------------------
 fn main() {
-    let conn = init_db().unwrap();
+    let cli = Cli::parse();
 
-    let period = get_current_period(&conn).unwrap();
-    let incomes = get_incomes(&conn, period).unwrap();
-    let expenses = get_expenses(&conn, period).unwrap();
+    if let Err(e) = run(&cli) {
+        eprintln!("Error : {}", e);
+        process::exit(1);
+    }
+}
 
-    if period == 0 {
-        create_period(&conn).expect("Error : cannot initialize the first period !");
+fn run(cli: &Cli) -> Result<()> {
+    get_money_format()?;
+    let schedule = get_period_schedule()?;
+    let ledger = Ledger::open_default()?;
+
+    // Going through the history must not roll over again on its own.
+    if !matches!(cli.command, Some(Commands::Undo { .. } | Commands::Redo | Commands::Logs | Commands::LogsAll)) {
+        ledger.catch_up(&schedule, Local::now().date_naive())?;
     }
 
-    let cli = Cli::parse();
+    let period = match &cli.period {
+        Some(reference) => ledger.find_period(reference)?.id,
+        None => ledger.get_current_period()?,
+    };
+    let incomes = ledger.get_incomes(period)?;
+    let expenses = ledger.get_expenses(period)?;
+    let format = cli.format;
 
     match &cli.command {
         Some(cmd) => {
             match cmd {
                 Commands::Database => {
-                    println!("{}", get_dbfile())
-                },
-                Commands::Estimate { label, amount } => {
-                    let estimate = parse_into_cents(amount);
-                    let opt_expense = find_expense_by_label(&expenses, &label);
+                    let version = ledger.schema_version()?;
+                    let report = json!({ "path": get_dbfile(), "schema_version": version, "supported_schema_version": SCHEMA_VERSION });
 
-                    match opt_expense {
-                        Some(exp) => override_estimate(&conn, &exp, estimate).expect("Error : Unable to update an expense !"),
-                        None => create_expense(&conn, period, &label, ExpenseType::ESTIMATED, estimate, 0).expect("Error : Unable to create a new expense !"),
+                    output(format, &report, || {
+                        println!("{}", get_dbfile());
+                        println!("Schema version {} (supported : {})", version, SCHEMA_VERSION);
+                    })?;
+                },
+                Commands::Estimate { label, amount, currency } => {
+                    let existing = find_expense_by_label(&expenses, label);
+                    let currency = line_currency(currency, label, existing.map(|e| e.currency.as_str()))?;
+                    let estimate = parse_in_currency(amount, &currency)?;
+
+                    match existing {
+                        Some(exp) => ledger.override_estimate(exp, estimate)?,
+                        None => ledger.create_expense(period, label, ExpenseType::ESTIMATED, estimate, 0, &currency)?,
                     }
                 },
 
-                Commands::Fixed { label, amount } => {
-                    let estimate = parse_into_cents(amount);
-                    let opt_expense = find_expense_by_label(&expenses, &label);
+                Commands::Fixed { label, amount, currency } => {
+                    let existing = find_expense_by_label(&expenses, label);
+                    let currency = line_currency(currency, label, existing.map(|e| e.currency.as_str()))?;
+                    let estimate = parse_in_currency(amount, &currency)?;
 
-                    match opt_expense {
-                        Some(exp) => override_estimate(&conn, &exp, estimate).expect("Error : Unable to update an expense !"),
-                        None => create_expense(&conn, period, &label, ExpenseType::FIXED, estimate, 0).expect("Error : Unable to create a new expense !"),
+                    match existing {
+                        Some(exp) => ledger.override_estimate(exp, estimate)?,
+                        None => ledger.create_expense(period, label, ExpenseType::FIXED, estimate, 0, &currency)?,
                     }
                 },
 
-                Commands::Income { label, amount } => {
-                    let cents = parse_into_cents(amount);
-                    create_income(&conn, period, &label, cents).expect("Error : Unable to create a new income !")
+                Commands::Income { label, amount, expected, on, recurring, currency } => {
+                    let existing = ledger.find_income(period, label)?;
+                    let currency = line_currency(currency, label, existing.as_ref().map(|i| i.currency.as_str()))?;
+                    let cents = parse_in_currency(amount, &currency)?;
+
+                    match existing {
+                        Some(_) if *expected || on.is_some() || *recurring => return Err(Error::IncomeExists(label.clone())),
+                        Some(inc) => ledger.override_income(&inc, cents)?,
+                        None if *expected || on.is_some() => {
+                            ledger.expect_income(period, label, cents, &currency, *on, *recurring)?;
+                            output(format, &ledger.get_income(period, label)?, || println!("Saved : New expected income line {} !", label))?;
+                        },
+                        None => {
+                            ledger.create_income(period, label, cents, &currency, *recurring)?;
+                            output(format, &ledger.get_income(period, label)?, || println!("Saved : New income line {} !", label))?;
+                        }
+                    }
+                },
+                Commands::Recurring { label, off } => {
+                    ledger.set_income_recurring(&ledger.get_income(period, label)?, !off)?;
+                },
+                Commands::Receive { label, amount } => {
+                    let income = ledger.get_income(period, label)?;
+                    let cents = match amount {
+                        Some(a) => parse_in_currency(a, &income.currency)?,
+                        None => income.pending(),
+                    };
+
+                    ledger.receive_income(&income, cents)?;
+                },
+                Commands::Incomes => {
+                    output(format, &incomes, || print_list("INCOME", &incomes))?;
+                },
+                Commands::RenameIncome { old, new } => {
+                    ledger.rename_income(&ledger.get_income(period, old)?, new)?;
+                },
+                Commands::RemoveIncome { label } => {
+                    ledger.remove_income(&ledger.get_income(period, label)?)?;
                 },
 
-                Commands::List { id } => {
-                    match id {
-                        Some(x) => {
-                            let period = get_period(&conn, *x).expect("Unable to find period");
-                            
-                            println!("PERIOD {} : {} -> {}", 
-                                period.id, 
-                                period.start_date, 
-                                period.end_date.unwrap_or("Current".to_string()));
-        
-                            let list_incomes = get_incomes(&conn, *x).unwrap();
-                            let list_expenses = get_expenses(&conn, *x).unwrap();
-                            list(&list_incomes, &list_expenses);
-                        },
+                Commands::List { id, by_category, depth } => {
+                    let shown = ledger.get_period(id.unwrap_or(period))?;
 
-                        None => { list(&incomes, &expenses) }
+                    let (list_incomes, list_expenses) = match id {
+                        Some(x) => (ledger.get_incomes(*x)?, ledger.get_expenses(*x)?),
+                        None => (incomes, expenses)
+                    };
+
+                    let (converted_incomes, converted_expenses, missing) = ledger.in_reporting_currency(&shown, &list_incomes, &list_expenses)?;
+                    warn_missing_rates(&missing);
+                    let balance = Balance::new(shown.id, &converted_incomes, &converted_expenses);
+                    let refunds = ledger.get_refunds(shown.id)?;
+                    let grouped = *by_category || depth.is_some();
+                    let tree = ledger.get_categories()?;
+
+                    let mut report = PeriodReport::new(&shown, &list_incomes, &list_expenses, &refunds, &balance);
+
+                    if grouped {
+                        report.categories = Some(CategoryReport::from_tree(&tree, &converted_expenses));
                     }
+
+                    output(format, &report, || {
+                        if id.is_some() || cli.period.is_some() {
+                            print_period_header(&shown);
+                        }
+
+                        if grouped {
+                            list_by_category(&list_incomes, &list_expenses, &converted_expenses, &tree, *depth);
+                        }
+
+                        else {
+                            list(&list_incomes, &list_expenses);
+                        }
+
+                        list_refunds(&refunds, &list_expenses);
+                    })?;
                 },
                 Commands::Logs => {
-                    let logs = get_current_logs(&conn);
-                    list_logs(&logs.expect("Error : cannot get logs !"));
+                    let logs = ledger.get_period_logs(period)?;
+                    output(format, &LogReport::from_logs(&logs), || list_logs(&logs))?;
                 },
                 Commands::LogsAll => {
-                    let logs = get_all_logs(&conn);
-                    list_logs(&logs.expect("Error : cannot get logs !"));
+                    let logs = ledger.get_all_logs()?;
+                    output(format, &LogReport::from_logs(&logs), || list_logs(&logs))?;
                 },
-                Commands::Period => {
-                    print!("{}", get_period(&conn, period).expect("Error : cannot find infos on the current period !"));
+                Commands::Period { action } => {
+                    match action {
+                        None => {
+                            let current = ledger.get_period(period)?;
+                            output(format, &current, || print!("{}", current))?;
+                        },
+                        Some(PeriodAction::List) => {
+                            let periods = ledger.get_periods()?;
+                            output(format, &periods, || print_list("PERIODS", &periods))?;
+                        },
+                        Some(PeriodAction::New { start, end, name }) => {
+                            let created = ledger.get_period(ledger.create_period(*start, *end, name.as_deref())?)?;
+                            output(format, &created, || {})?;
+                        },
+                        Some(PeriodAction::Name { id, name }) => {
+                            ledger.set_period_name(&ledger.get_period(*id)?, name.as_deref())?;
+                            output(format, &ledger.get_period(*id)?, || {})?;
+                        },
+                        Some(PeriodAction::Dates { id, start, end }) => {
+                            let target = ledger.get_period(*id)?;
+                            ledger.set_period_dates(&target, start.unwrap_or(target.start_date), end.or(target.end_date))?;
+                            output(format, &ledger.get_period(*id)?, || {})?;
+                        },
+                        Some(PeriodAction::Reopen { id }) => {
+                            ledger.reopen_period(&ledger.get_period(*id)?)?;
+                            output(format, &ledger.get_period(*id)?, || {})?;
+                        },
+                        Some(PeriodAction::Delete { id }) => {
+                            ledger.delete_period(&ledger.get_period(*id)?)?;
+                        },
+                    }
                 },
                 Commands::Remove { label } => {
-                    let expense = find_expense_by_label(&expenses, label.as_str())
-                                           .expect("Error : no expense found, unable to remove it.");
-                    remove_expense(&conn, &expense).expect("Error : unable to remove expense.");
+                    ledger.remove_expense(&ledger.get_expense(period, label)?)?;
                 },
                 Commands::Rename { old, new } => {
-                    let opt_expense = find_expense_by_label(&expenses, old.as_str());
+                    ledger.rename_expense(&ledger.get_expense(period, old)?, new)?;
+                },
+                Commands::Categorize { label, path } => {
+                    let expense = ledger.get_expense(period, label)?;
+
+                    match path {
+                        Some(p) => ledger.set_expense_category(&expense, p)?,
+                        None => ledger.clear_expense_category(&expense)?,
+                    }
+                },
+                Commands::Rates => {
+                    let rates = ledger.get_latest_rates()?;
+                    output(format, &rates, || print_list("EXCHANGE RATES, FOR 1 EUR", &rates))?;
+                },
+                Commands::Categories => {
+                    let tree = ledger.get_categories()?;
+                    let categories: Vec<_> = tree.walk()
+                        .into_iter()
+                        .map(|(c, depth)| json!({ "id": c.id, "parent_id": c.parent_id, "name": c.name, "path": tree.path(c.id), "depth": depth }))
+                        .collect();
+
+                    output(format, &categories, || {
+                        for (category, depth) in tree.walk() {
+                            println!("{}{}", "  ".repeat(depth - 1), category.name);
+                        }
+                    })?;
+                },
+                Commands::Import { format: import_format } => {
+                    let (entries, options, source) = match import_format {
+                        ImportFormat::Csv { file, date, amount, description, date_format, decimal_separator, delimiter, no_header, inverted, options } => {
+                            let mapping = CsvMapping {
+                                date_column: date.clone(),
+                                amount_column: amount.clone(),
+                                description_column: description.clone(),
+                                date_format: date_format.clone(),
+                                decimal_separator: *decimal_separator,
+                                delimiter: *delimiter as u8,
+                                has_headers: !no_header,
+                                inverted: *inverted,
+                            };
+
+                            (csv::read_statement(file, &mapping)?, options, file)
+                        },
+                        ImportFormat::Ofx { file, options } => (ofx::read_statement(file)?, options, file),
+                        ImportFormat::Qif { file, date_format, options } => (qif::read_statement(file, date_format)?, options, file),
+                        ImportFormat::Camt { file, options } => (camt::read_statement(file)?, options, file),
+                        ImportFormat::Mt940 { file, options } => (mt940::read_statement(file)?, options, file),
+                        ImportFormat::Worksheet { file, start } => {
+                            let periods = worksheet::read_worksheet(file, *start)?;
+                            let ids = ledger.import_worksheet(&periods, &file.display().to_string())?;
+                            let created = ids.iter().map(|id| ledger.get_period(*id)).collect::<Result<Vec<Period>>>()?;
+
+                            return output(format, &created, || show_worksheet_import(&periods, &ids));
+                        },
+                        ImportFormat::Rates { file } => {
+                            let rates = rates::read_rates(file)?;
+                            let imported = ledger.import_rates(&rates, &file.display().to_string())?;
 
-                    match opt_expense {
-                        Some(exp) => rename_expense(&conn, &exp, new).expect("Error : Unable to update an expense !"),
-                        None => panic!("Error : no expense line found"),
+                            return output(format, &json!({ "imported": imported }), || println!("Imported {} exchange rates.", imported));
+                        },
+                        ImportFormat::Journal { file, options } => {
+                            let postings = journal::read_journal(file)?;
+                            let summary = ledger.import_journal(&postings, &load_rules(options)?, &file.display().to_string())?;
+
+                            return output(format, &summary, || show_import_summary(&summary));
+                        },
+                    };
+
+                    let summary = ledger.import_entries(period, &entries, &load_rules(options)?, &source.display().to_string())?;
+                    output(format, &summary, || show_import_summary(&summary))?;
+                },
+                Commands::Export { format: export_format } => {
+                    match export_format {
+                        ExportFormat::Csv { selection, output } => {
+                            let periods = select_periods(&ledger, selection, period)?;
+                            export::csv::write_periods(open_output(output)?, &periods)?;
+                        },
+                        ExportFormat::Journal { selection, output } => {
+                            let periods = select_periods(&ledger, selection, period)?;
+                            export::journal::write_journal(open_output(output)?, &periods)?;
+                        },
+                        ExportFormat::Beancount { selection, output } => {
+                            let periods = select_periods(&ledger, selection, period)?;
+                            export::beancount::write_beancount(open_output(output)?, &periods, &REPORTING_CURRENCY)?;
+                        },
                     }
                 },
+                Commands::Undo { count } => {
+                    let undone = ledger.undo(count.unwrap_or(1))?;
+
+                    output(format, &LogReport::from_logs(&undone), || {
+                        if undone.is_empty() {
+                            println!("Nothing to undo.");
+                        }
+
+                        for log in &undone {
+                            println!("Undone : {}", log.description());
+                        }
+                    })?;
+                },
+                Commands::Redo => {
+                    let redone = ledger.redo()?;
+                    let redone = redone.as_slice();
+
+                    output(format, &LogReport::from_logs(redone).first(), || {
+                        match redone.first() {
+                            Some(log) => println!("Redone : {}", log.description()),
+                            None => println!("Nothing to redo."),
+                        }
+                    })?;
+                },
                 Commands::Roll => {
-                    end_period(&conn, period).expect("Error : cannot set an end date for the current period !");
-                    create_period(&conn).expect("Error : cannot initialize a new period !");
-                    copy_fixed_and_estimates(&conn).expect("Error : cannot initialize expenses for the new period !");
+                    let new_period = ledger.get_period(ledger.roll()?)?;
+                    output(format, &new_period, || {})?;
                 },
-                Commands::Spend { label, amount } => {
+                Commands::Transactions { label } => {
+                    let expense = ledger.get_expense(period, label)?;
+                    let transactions = ledger.get_transactions(&expense)?;
+
+                    output(format, &ExpenseReport { expense: &expense, transactions: &transactions }, || {
+                        println!("{}", expense);
+                        print_list("TRANSACTIONS", &transactions);
+                    })?;
+                },
+                Commands::Amend { id, amount, date, memo } => {
+                    let transaction = ledger.get_transaction(*id)?;
+                    let amount = amount.as_deref().map(|a| parse_in_currency(a, &transaction.currency)).transpose()?;
+
+                    ledger.amend_transaction(&transaction, amount, *date, memo.as_deref())?;
+
+                    let expense = ledger.get_expense_by_id(transaction.expense_id)?;
+                    output(format, &expense, || println!("{}", expense))?;
+                },
+                Commands::Delete { id } => {
+                    let transaction = ledger.get_transaction(*id)?;
+
+                    ledger.delete_transaction(&transaction)?;
+
+                    let expense = ledger.get_expense_by_id(transaction.expense_id)?;
+                    output(format, &expense, || println!("{}", expense))?;
+                },
+                Commands::Refund { label, amount, currency, date, memo } => {
+                    let expense = ledger.get_expense(period, label)?;
+                    let currency = payment_currency(currency, Some(&expense.currency))?;
+                    let refunded = parse_in_currency(amount, &currency)?;
+
+                    ledger.refund(&expense, refunded, &currency, *date, memo.as_deref())?;
+                },
+                Commands::Spend { label, amount, currency, date, memo } => {
                     match amount {
                         Some(mtt) => {
-                            let spent = parse_into_cents(mtt);
-                            let opt_expense = find_expense_by_label(&expenses, &label);
-        
-                            match opt_expense {
-                                Some(exp) => increment_spending(&conn, &exp, spent).expect("Error : Unable to save the spending !"),
-                                None => create_expense(&conn, period, &label, ExpenseType::UNPLANNED, spent, spent).expect("Error : Unable to create a new expense !"),
-                            }
+                            let existing = find_expense_by_label(&expenses, label);
+                            let currency = payment_currency(currency, existing.map(|e| e.currency.as_str()))?;
+                            let spent = parse_in_currency(mtt, &currency)?;
+
+                            ledger.spend(period, label, spent, &currency, *date, memo.as_deref())?;
                         },
                         None => {
-                            let expense = find_expense_by_label(&expenses, &label).expect("Error : no expense found, unable to remove it.");
-                            spend_all(&conn, &expense).expect("Error : unable to spend all on expense.");
+                            ledger.spend_all(&ledger.get_expense(period, label)?, *date, memo.as_deref())?;
                         }
                     }
                 },
             }
         },
         None => {
-            show_balance(&incomes, &expenses)
+            let (incomes, expenses, missing) = ledger.in_reporting_currency(&ledger.get_period(period)?, &incomes, &expenses)?;
+            warn_missing_rates(&missing);
+            output(format, &Balance::new(period, &incomes, &expenses), || show_balance(&incomes, &expenses))?;
         }
     }
+
+    Ok(())
+}
+
+/// Print a result, as text through `text` or serialized in the requested format.
+fn output<T: Serialize>(format: Format, value: &T, text: impl FnOnce()) -> Result<()> {
+    match format {
+        Format::Text => text(),
+        Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Format::Yaml => print!("{}", serde_yaml::to_string(value)?),
+    }
+
+    Ok(())
+}
+
+fn print_period_header(period: &Period) {
+    println!("PERIOD {}{} : {} -> {}",
+        period.id,
+        period.name.as_ref().map(|n| format!(" ({})", n)).unwrap_or_default(),
+        period.start_date,
+        period.end_date.map(|d| d.to_string()).unwrap_or("Current".to_string()));
 }
 
 /// Print the logbook.
-fn list_logs(logs: &Vec<Log>) {
+fn list_logs(logs: &[Log]) {
     for line in logs {
         println!("{}", line)
     }
 }
 
 /// Print a detailed account.
-fn list(incomes: &Vec<Income>, expenses: &Vec<Expense>) {
-    print_list("INCOME", &incomes);
-    list_expenses(&expenses);
+fn list(incomes: &[Income], expenses: &[Expense]) {
+    print_list("INCOME", incomes);
+    list_expenses(expenses);
 }
 
 /// List every expense.
-fn list_expenses(source: &Vec<Expense>) {
+fn list_expenses(source: &[Expense]) {
     let mut estimated: Vec<&Expense> = Vec::new();
     let mut fixed: Vec<&Expense> = Vec::new();
     let mut unplanned: Vec<&Expense> = Vec::new();
@@ -258,421 +856,191 @@ fn list_expenses(source: &Vec<Expense>) {
         }
     }
 
-    estimated.sort_by(|a, b| b.spent.cmp(&a.spent));
-    fixed.sort_by(|a, b| b.spent.cmp(&a.spent));
-    unplanned.sort_by(|a, b| b.spent.cmp(&a.spent));
+    estimated.sort_by_key(|e| std::cmp::Reverse(e.spent));
+    fixed.sort_by_key(|e| std::cmp::Reverse(e.spent));
+    unplanned.sort_by_key(|e| std::cmp::Reverse(e.spent));
 
     print_list("FIXED MONTHLY EXPENSES", &fixed);
     print_list("VARIABLE MONTHLY EXPENSES", &estimated);
     print_list("UNPLANNED MONTHLY EXPENSES", &unplanned);
 }
 
-fn find_expense_by_label<'a>(expenses: &'a Vec<Expense>, label: &'a str) -> Option<&'a Expense> {
-    return expenses.iter().find(|exp| exp.label == label);
-}
+/// List every expense, grouped by category with subtotals in the reporting currency.
+/// With a depth, only the subtotals of the categories down to that depth are shown.
+fn list_by_category(incomes: &[Income], expenses: &[Expense], converted: &[Expense], tree: &CategoryTree, depth: Option<usize>) {
+    print_list("INCOME", incomes);
+    println!("--------------- EXPENSES BY CATEGORY ---------------");
 
-fn print_list<T: fmt::Display>(label: &str, source: &Vec<T>) {
-    println!("--------------- {} ---------------", label);
-    
-    for item in source {
-        println!("{}", item);
-    }
-}
+    let subtotals = tree.subtotals(converted);
 
-/// Print the current balance.
-fn show_balance(incomes: &Vec<Income>, expenses: &Vec<Expense>) {
-    println!("Current balance : {}", print_in_currency(get_balance(incomes, expenses)));
-    println!("Estimated balance at end of period : {}", print_in_currency(get_end_of_period_estimate(incomes, expenses)));
-}
-
-/// Get the current balance (all incomes, minus all expenses spent)
-fn get_balance(incomes: &Vec<Income>, expenses: &Vec<Expense>) -> i64 {
-    let income_sum: i64 = incomes.iter()
-                        .map(|x| x.value)
-                        .sum(); 
-                           
-    let expense_sum: i64 = expenses.iter()
-                        .map(|x| x.spent)
-                        .sum();
-
-    return income_sum - expense_sum;
-}
-
-/// Get the current balance (all incomes, minus all expenses estimated)
-fn get_end_of_period_estimate(incomes: &Vec<Income>, expenses: &Vec<Expense>) -> i64 {
-    let income_sum: i64 = incomes.iter()
-                        .map(|x| x.value)
-                        .sum(); 
-                           
-    let expense_sum: i64 = expenses.iter()
-                        .map(|x| x.estimate)
-                        .sum();
-
-    return income_sum - expense_sum;
-}
-
-/// Increase the amount spent on an expense line to match the estimate.
-fn spend_all(conn: &Connection, expense: &Expense) -> Result<()> {
-    return override_spending(&conn, &expense, expense.estimate);
-}
-
-// ------------------------------------------------------------
-// DATABASE
-// ------------------------------------------------------------
-fn init_db() -> Result<Connection> {
-    let conn = Connection::open(get_dbfile())?;
-
-    conn.execute(
-        "create table if not exists periods (
-            id integer primary key AUTOINCREMENT,
-            start_date date not null,
-            end_date date
-        )",
-        (),
-    )?;
-
-    conn.execute(
-        "create table if not exists incomes (
-            id integer primary key AUTOINCREMENT,
-            period_id integer not null references periods(id),
-            label text not null,
-            value BIGINT not null
-        )",
-        (),
-    )?;
-
-    conn.execute(
-        "create table if not exists expenses (
-            id integer primary key AUTOINCREMENT,
-            period_id integer not null references periods(id),
-            label text not null,
-            type text not null,
-            estimate BIGINT not null,
-            spent BIGINT not null
-        )",
-        (),
-    )?;
-    
-    conn.execute(
-        "create table if not exists logs (
-            id integer primary key AUTOINCREMENT,
-            period_id integer not null,
-            timer timestamp not null,
-            action text not null,
-            arg1 text,
-            arg2 text,
-            arg3 text
-        )",
-        (),
-    )?;
-
-    Ok(conn)
-}
+    for (category, level) in tree.walk() {
+        if depth.is_some_and(|d| level > d) {
+            continue;
+        }
 
-/// Create a new line of log with one variable parameter
-fn create_log_one_param(conn: &Connection, action: &str, param1: &str) -> Result<()> {    
-    let period_id = get_current_period(&conn).expect("Unable to find period !");
+        let Some(subtotal) = subtotals.get(&category.id) else { continue };
+        let indent = "  ".repeat(level - 1);
 
-    conn.execute(
-        "INSERT INTO logs (period_id, timer, action, arg1) values (?1, CURRENT_TIMESTAMP, ?2, ?3)",
-        (period_id, action, param1),
-    )?;
+        println!("{}{} : {} spent out of {}",
+            indent,
+            category.name,
+            print_in_currency(subtotal.spent),
+            print_in_currency(subtotal.estimate));
 
-    Ok(())
-}
+        if depth.is_none() {
+            for expense in expenses.iter().filter(|e| e.category_id == Some(category.id)) {
+                println!("{}  - {}", indent, expense);
+            }
+        }
+    }
 
-/// Create a new line of log with two variable parameters
-fn create_log_two_params(conn: &Connection, action: &str, param1: &str, param2: &str) -> Result<()> {    
-    let period_id = get_current_period(&conn).expect("Unable to find period !");
+    let uncategorized: Vec<&Expense> = converted.iter().filter(|e| e.category_id.is_none()).collect();
 
-    conn.execute(
-        "INSERT INTO logs (period_id, timer, action, arg1, arg2) values (?1, CURRENT_TIMESTAMP, ?2, ?3, ?4)",
-        (period_id, action, param1, param2),
-    )?;
+    if !uncategorized.is_empty() {
+        println!("Uncategorized : {} spent out of {}",
+            print_in_currency(uncategorized.iter().map(|e| e.spent).sum()),
+            print_in_currency(uncategorized.iter().map(|e| e.estimate).sum()));
 
-    Ok(())
+        if depth.is_none() {
+            for expense in expenses.iter().filter(|e| e.category_id.is_none()) {
+                println!("  - {}", expense);
+            }
+        }
+    }
 }
 
-/// Create a new line of log with three variable parameters
-fn create_log_three_params(conn: &Connection, action: &str, param1: &str, param2: &str, param3: &str) -> Result<()> {    
-    let period_id = get_current_period(&conn).expect("Unable to find period !");
-
-    conn.execute(
-        "INSERT INTO logs (period_id, timer, action, arg1, arg2, arg3) values (?1, CURRENT_TIMESTAMP, ?2, ?3, ?4, ?5)",
-        (period_id, action, param1, param2, param3),
-    )?;
-
-    Ok(())
-}
+/// List the refunds of the period, if any.
+fn list_refunds(refunds: &[Transaction], expenses: &[Expense]) {
+    if refunds.is_empty() {
+        return;
+    }
 
-/// Create a new income
-fn create_income(conn: &Connection, period: u32, label: &str, value: i64) -> Result<()> {    
-    conn.execute(
-        "INSERT INTO incomes (period_id, label, value) values (?1, ?2, ?3)",
-        (period, label, value),
-    )?;
-
-    create_log_two_params(&conn, "ADD_INCOME", label, &print_in_currency(value))
-        .expect("Unable to create ADD_INCOME log : ");
-        
-    println!("Saved : New income line {} !", label);
-    Ok(())
-}
+    println!("--------------- REFUNDS ---------------");
 
-/// Create a new expense
-fn create_expense(conn: &Connection, period: u32, label: &str, expense_type: ExpenseType, estimate: i64, spent: i64) -> Result<()> {    
-    conn.execute(
-        "INSERT INTO expenses (period_id, label, type, estimate, spent) values (?1, ?2, ?3, ?4, ?5)",
-        (period, label, expense_type.to_string(), estimate, spent),
-    )?;
+    for refund in refunds {
+        let label = expenses.iter()
+            .find(|e| e.id == refund.expense_id)
+            .map_or("", |e| e.label.as_str());
 
-    create_log_three_params(&conn, "ADD_EXPENSE", &label, &print_in_currency(estimate), &print_in_currency(spent))
-        .expect("Unable to create ADD_EXPENSE log : ");
-    Ok(())
+        println!("{} : {}", label, refund);
+    }
 }
 
-/// Set a new estimate for an expense line.
-fn override_estimate(conn: &Connection, expense: &Expense, new_estimate: i64) -> Result<()> {
-    conn.execute(
-        "UPDATE expenses SET estimate = ?1 WHERE id = ?2",
-        (new_estimate, expense.id),
-    )?;
-
-    create_log_two_params(&conn, "UPDATE_ESTIMATE", &expense.label, &print_in_currency(new_estimate))
-        .expect("Unable to create UPDATE_ESTIMATE log : ");
-    Ok(())
+/// Currency of the amount given for an income or expense line : the one asked for,
+/// the one of the line if it exists, or else the reporting currency.
+fn line_currency(asked: &Option<String>, label: &str, existing: Option<&str>) -> Result<String> {
+    let asked = asked.as_deref().map(parse_code).transpose()?;
+
+    return match (asked, existing) {
+        (Some(a), Some(e)) if a != e => Err(Error::CurrencyMismatch(label.to_string(), e.to_string())),
+        (Some(a), _) => Ok(a),
+        (None, Some(e)) => Ok(e.to_string()),
+        (None, None) => Ok(REPORTING_CURRENCY.clone()),
+    };
 }
 
-/// Rename an expense line
-fn rename_expense(conn: &Connection, expense: &Expense, new_label: &String) -> Result<()> {
-    conn.execute(
-        "UPDATE expenses SET label = ?1 WHERE id = ?2",
-        (new_label, expense.id),
-    )?;
-
-    create_log_two_params(&conn, "RENAME_ESTIMATE", &expense.label, &new_label)
-        .expect("Unable to create RENAME_ESTIMATE log : ");
-    Ok(())
+/// Currency of a payment : the one asked for, the one of its line if it exists, or else the reporting currency.
+fn payment_currency(asked: &Option<String>, line: Option<&str>) -> Result<String> {
+    return match asked {
+        Some(a) => parse_code(a),
+        None => Ok(line.map_or_else(|| REPORTING_CURRENCY.clone(), |l| l.to_string())),
+    };
 }
 
-/// Removes an expense
-fn remove_expense(conn: &Connection, expense: &Expense) -> Result<()> {    
-    conn.execute(
-        "DELETE FROM expenses WHERE id = ?",
-        [expense.id],
-    )?;
-
-    create_log_one_param(&conn, "REMOVE_EXPENSE", &expense.label)
-        .expect("Unable to create REMOVE_EXPENSE log : ");
-    Ok(())
+fn find_expense_by_label<'a>(expenses: &'a [Expense], label: &'a str) -> Option<&'a Expense> {
+    return expenses.iter().find(|exp| exp.label == label);
 }
 
-/// Copy fixed and estimated expense lines when we roll over to a new period.
-/// The "spent" amount is initialized at zero.
-fn copy_fixed_and_estimates(conn: &Connection) -> Result<()> {
-    let new_period_id: u32 = get_current_period(conn).expect("Unable to find a period !");
-    let old_period_id = new_period_id - 1;
+fn print_list<T: fmt::Display>(label: &str, source: &[T]) {
+    println!("--------------- {} ---------------", label);
     
-    conn.execute(
-        "INSERT INTO expenses (period_id, label, type, estimate, spent)
-              SELECT period_id + 1, label, type, estimate, 0 
-              FROM expenses e2 
-              WHERE e2.period_id = ?
-              AND e2.type in ('FIXED', 'ESTIMATED') ",
-        [old_period_id],
-    )?;
-
-    Ok(())
-}
-
-
-fn get_current_period(conn: &Connection) -> Result<u32> {
-    let mut stmt = conn.prepare(
-        "SELECT ifnull(max(p.id), 0) FROM periods p"
-    )?;
-
-    let mut rows = stmt.query([])?;
-    let mut res = 0;
-
-    if let Some(row) = rows.next()? {
-        res = row.get(0)?;
+    for item in source {
+        println!("{}", item);
     }
-
-    Ok(res)
 }
 
-/// Set an end date for a period
-fn end_period(conn: &Connection, id: u32) -> Result<()> {    
-    conn.execute(
-        "UPDATE periods SET end_date = DATE('now') WHERE id = ?",
-        [id],
-    )?;
-
-    create_log_one_param(&conn, "END_PERIOD", &id.to_string())
-        .expect("Unable to create END_PERIOD log : ");
-    Ok(())
+/// Label rules given on the command line, if any.
+fn load_rules(options: &ImportOptions) -> Result<LabelRules> {
+    return match &options.rules {
+        Some(path) => LabelRules::load(path),
+        None => Ok(LabelRules::default()),
+    };
 }
 
-/// Create a new period
-fn create_period(conn: &Connection) -> Result<()> {    
-    conn.execute(
-        "INSERT INTO periods (start_date) values (DATE('now'))",
-        (),
-    )?;
-
-    create_log_one_param(&conn, "START_PERIOD", &conn.last_insert_rowid().to_string())
-        .expect("Unable to create START_PERIOD log : ");
-    Ok(())
-}
-
-/// Get all info on a period
-fn get_period(conn: &Connection, id: u32) -> Result<Period> {
-    let mut stmt = conn.prepare(
-        "SELECT id, start_date, end_date FROM periods p WHERE p.id = ?"
-    )?;
-
-    return stmt.query_row([id], |row| {
-        Ok(Period {
-            id: row.get(0)?,
-            start_date: row.get(1)?,
-            end_date: row.get(2)?,
-        })
-    });
-}
+/// Print the expense lines and incomes touched by an import.
+fn show_import_summary(summary: &ImportSummary) {
+    for (title, lines) in [("EXPENSES", &summary.expenses), ("INCOME", &summary.incomes)] {
+        if lines.is_empty() {
+            continue;
+        }
 
-/// Get all saved incomes
-fn get_incomes(conn: &Connection, period: u32) -> Result<Vec<Income>> {
-    let mut stmt = conn.prepare(
-        "SELECT i.id, i.label, i.value FROM incomes i WHERE i.period_id = ?"
-    )?;
+        println!("--------------- {} ---------------", title);
 
-    let incomes_iter = stmt.query_map([period], |row| {
-        Ok(Income {
-            _id: row.get(0)?,
-            label: row.get(1)?,
-            value: row.get(2)?,
-        })
-    })?;
+        for (label, line) in lines {
+            println!("{} : {} entries, {}{}",
+                label,
+                line.count,
+                print_in_currency(line.total),
+                if line.created { " (new)" } else { "" });
+        }
+    }
 
-    let mut incomes: Vec<Income> = Vec::new();
+    if summary.duplicates > 0 {
+        println!("Skipped {} entries already imported.", summary.duplicates);
+    }
 
-    for elem in incomes_iter {
-        incomes.push(elem.unwrap());
+    if summary.outside_period > 0 {
+        println!("Skipped {} entries dated outside of the period.", summary.outside_period);
     }
 
-    return Ok(incomes);
+    for id in &summary.created_periods {
+        println!("Created period {}.", id);
+    }
 }
 
-/// Get all saved expenses
-fn get_expenses(conn: &Connection, period: u32) -> Result<Vec<Expense>> {
-    let mut stmt = conn.prepare(
-        "SELECT e.id, e.label, e.type, e.estimate, e.spent FROM expenses e WHERE e.period_id = ?"
-    )?;
-
-    let expenses_iter = stmt.query_map([period], |row| {
-        let raw_expense_type = row.get::<_, String>(2)?;
-
-        let expense_type = match raw_expense_type.as_str() {
-            "FIXED" => ExpenseType::FIXED,
-            "ESTIMATED" => ExpenseType::ESTIMATED,
-            "UNPLANNED" => ExpenseType::UNPLANNED,
-            _ => panic!("Unknown expense type !")
-        };
-
-        Ok(Expense {
-            id: row.get(0)?,
-            label: row.get(1)?,
-            estimate: row.get(3)?, // in cents.
-            spent: row.get(4)?, // in cents.
-            expense_type: expense_type
-        })
-    })?;
-
-    let mut expenses: Vec<Expense> = Vec::new();
-
-    for elem in expenses_iter {
-        expenses.push(elem.unwrap());
+/// Print the periods created from a worksheet.
+fn show_worksheet_import(periods: &[WorksheetPeriod], ids: &[u32]) {
+    for (period, id) in periods.iter().zip(ids) {
+        let incomes = period.lines.iter().filter(|l| matches!(l.kind, LineKind::Income)).count();
+
+        println!("Period {} ({} -> {}, sheet '{}') : {} expense lines, {} incomes",
+            id,
+            period.start_date,
+            period.end_date,
+            period.sheet,
+            period.lines.len() - incomes,
+            incomes);
     }
-
-    return Ok(expenses);
 }
 
-/// Increase the amount spent on an expense line.
-fn increment_spending(conn: &Connection, expense: &Expense, amount: i64) -> Result<()> {
-    conn.execute(
-        "UPDATE expenses SET spent = spent + ?1 WHERE id = ?2",
-        (amount, expense.id),
-    )?;
+/// Periods picked on the command line, with their incomes and expense lines.
+fn select_periods(ledger: &Ledger, selection: &PeriodSelection, current: u32) -> Result<Vec<PeriodData>> {
+    let periods = match selection {
+        PeriodSelection { all: true, .. } => ledger.get_periods()?,
+        PeriodSelection { from: None, to: None, .. } => vec![ledger.get_period(current)?],
+        PeriodSelection { from, to, .. } => ledger.get_periods_between(*from, *to)?,
+    };
 
-    create_log_two_params(&conn, "SPEND", &expense.label, &print_in_currency(amount))
-        .expect("Unable to create SPEND log : ");
-    Ok(())
+    return periods.into_iter().map(|p| ledger.get_period_data(p)).collect();
 }
 
-/// Increase the amount spent on an expense line.
-fn override_spending(conn: &Connection, expense: &Expense, new_amount: i64) -> Result<()> {
-    conn.execute(
-        "UPDATE expenses SET spent = ?1 WHERE id = ?2",
-        (new_amount, expense.id),
-    )?;
-
-    create_log_two_params(&conn, "OVERRIDE_SPENDING", &expense.label, &print_in_currency(new_amount))
-        .expect("Unable to create OVERRIDE_SPENDING log : ");
-    Ok(())
+/// Where to write an export.
+fn open_output(output: &ExportOutput) -> Result<Box<dyn Write>> {
+    return Ok(match &output.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    });
 }
 
-// ------------------------------------------------------------
-// LOGS
-// ------------------------------------------------------------
-fn get_all_logs(conn: &Connection) -> Result<Vec<Log>> {
-    let mut stmt = conn.prepare(
-        "SELECT l.id, l.timer, l.action, l.arg1, l.arg2, l.arg3 FROM logs l ORDER BY l.id desc "
-    )?;
-
-    let log_iter = stmt.query_map([], |row| {
-        Ok(Log {
-            id: row.get(0)?,
-            timer: row.get(1)?,
-            action: row.get(2)?, 
-            arg1: row.get(3)?,
-            arg2: row.get(4)?,
-            arg3: row.get(5)?,
-        })
-    })?;
-
-    let mut logs: Vec<Log> = Vec::new();
-
-    for logline in log_iter {
-        logs.push(logline.unwrap());
+/// Warn about the lines left out of the totals for want of an exchange rate.
+fn warn_missing_rates(currencies: &[String]) {
+    for currency in currencies {
+        eprintln!("Warning : no exchange rate for {}, the lines kept in {} are left out of the totals. Import some with `ebenezer import rates`.", currency, currency);
     }
-
-    return Ok(logs);
 }
 
-fn get_current_logs(conn: &Connection) -> Result<Vec<Log>> {
-    let period_id = get_current_period(&conn).expect("Unable to find period !");
-
-    let mut stmt = conn.prepare(
-        "SELECT l.id, l.timer, l.action, l.arg1, l.arg2, l.arg3 FROM logs l WHERE l.period_id = ? ORDER BY l.id desc "
-    )?;
-
-    let log_iter = stmt.query_map([period_id], |row| {
-        Ok(Log {
-            id: row.get(0)?,
-            timer: row.get(1)?,
-            action: row.get(2)?, 
-            arg1: row.get(3)?,
-            arg2: row.get(4)?,
-            arg3: row.get(5)?,
-        })
-    })?;
-
-    let mut logs: Vec<Log> = Vec::new();
-
-    for logline in log_iter {
-        logs.push(logline.unwrap());
-    }
-
-    return Ok(logs);
+/// Print the current balance.
+fn show_balance(incomes: &[Income], expenses: &[Expense]) {
+    println!("Current balance : {}", print_in_currency(get_balance(incomes, expenses)));
+    println!("Estimated balance at end of period : {}", print_in_currency(get_end_of_period_estimate(incomes, expenses)));
 }
-
@@ -0,0 +1,166 @@
+use std::fmt;
+use rusqlite::Connection;
+
+// ------------------------------------------------------------
+// MIGRATIONS
+// ------------------------------------------------------------
+/// Ordered schema upgrades. Step `n` brings a database from version `n` to version `n + 1`,
+/// the resulting version being stored in `PRAGMA user_version`.
+/// Never edit a step once released : append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1 : initial schema. Databases created before versioning already have these tables.
+    "create table if not exists periods (
+        id integer primary key AUTOINCREMENT,
+        start_date date not null,
+        end_date date
+    );
+
+    create table if not exists incomes (
+        id integer primary key AUTOINCREMENT,
+        period_id integer not null references periods(id),
+        label text not null,
+        value BIGINT not null
+    );
+
+    create table if not exists expenses (
+        id integer primary key AUTOINCREMENT,
+        period_id integer not null references periods(id),
+        label text not null,
+        type text not null,
+        estimate BIGINT not null,
+        spent BIGINT not null
+    );
+
+    create table if not exists logs (
+        id integer primary key AUTOINCREMENT,
+        period_id integer not null,
+        timer timestamp not null,
+        action text not null,
+        arg1 text,
+        arg2 text,
+        arg3 text
+    );",
+
+    // 2 : individual spending transactions. Former totals become one transaction dated at the start of their period.
+    "create table transactions (
+        id integer primary key AUTOINCREMENT,
+        expense_id integer not null references expenses(id) on delete cascade,
+        amount BIGINT not null,
+        date date not null,
+        memo text,
+        created_at timestamp not null
+    );
+
+    create index transactions_expense on transactions(expense_id);
+
+    insert into transactions (expense_id, amount, date, created_at)
+        select e.id, e.spent, p.start_date, CURRENT_TIMESTAMP
+        from expenses e
+        join periods p on p.id = e.period_id
+        where e.spent <> 0;
+
+    alter table expenses drop column spent;",
+
+    // 3 : undo history. `changes` holds the rows touched by the command, before and after.
+    // `undone` is 0 for active commands, 1 for undone ones and 2 for undone ones that can no longer be redone.
+    "alter table logs add column changes text;
+    alter table logs add column undone integer not null default 0;",
+
+    // 4 : expected incomes. Incomes recorded so far were all counted as received.
+    "alter table incomes add column received BIGINT not null default 0;
+    alter table incomes add column expected_date date;
+    update incomes set received = value;",
+
+    // 5 : recurring incomes, copied into the new period on roll.
+    "alter table incomes add column recurring integer not null default 0;",
+
+    // 6 : hierarchical categories for expense lines.
+    "create table categories (
+        id integer primary key AUTOINCREMENT,
+        parent_id integer references categories(id),
+        name text not null
+    );
+
+    alter table expenses add column category_id integer references categories(id);",
+
+    // 7 : references of the statement entries already imported, to skip them on the next import.
+    "create table imports (
+        id integer primary key AUTOINCREMENT,
+        reference text not null unique,
+        created_at timestamp not null
+    );",
+
+    // 8 : refunds, stored as negative transactions flagged apart from plain corrections.
+    "alter table transactions add column refund integer not null default 0;",
+
+    // 9 : currencies and exchange rates. Amounts recorded so far get the reporting currency when the ledger opens.
+    // `line_amount` is the amount of a transaction in the currency of its expense line.
+    "alter table incomes add column currency text;
+    alter table expenses add column currency text;
+    alter table transactions add column currency text;
+    alter table transactions add column line_amount BIGINT;
+    update transactions set line_amount = amount;
+
+    create table rates (
+        id integer primary key AUTOINCREMENT,
+        date date not null,
+        currency text not null,
+        rate real not null,
+        unique (currency, date)
+    );",
+
+    // 10 : optional period names, such as 'October 2026'.
+    "alter table periods add column name text;",
+];
+
+/// The schema version this binary expects.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sql(rusqlite::Error),
+    TooRecent { found: u32, supported: u32 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::Sql(e) => write!(f, "Database upgrade failed : {}", e),
+            MigrationError::TooRecent { found, supported } => {
+                write!(f, "Database schema version {} is newer than the one supported by this binary ({}). Please upgrade Ebenezer.",
+                    found,
+                    supported)
+            }
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sql(e)
+    }
+}
+
+/// Read the schema version stored in the database.
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    return conn.pragma_query_value(None, "user_version", |row| row.get(0));
+}
+
+/// Bring the database up to date, one transaction per step.
+/// A failing step leaves the database at the last successful version.
+pub fn migrate(conn: &mut Connection) -> Result<(), MigrationError> {
+    let current = schema_version(conn)?;
+
+    if current > SCHEMA_VERSION {
+        return Err(MigrationError::TooRecent { found: current, supported: SCHEMA_VERSION });
+    }
+
+    for (index, step) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(step)?;
+        tx.pragma_update(None, "user_version", index as u32 + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
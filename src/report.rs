@@ -0,0 +1,125 @@
+use serde::Serialize;
+use crate::categories::CategoryTree;
+use crate::ledger::{get_balance, get_end_of_period_estimate};
+use crate::settings::REPORTING_CURRENCY;
+use crate::structs::{Expense, Income, Log, Period, Transaction};
+
+// ------------------------------------------------------------
+// REPORTS
+// ------------------------------------------------------------
+// Serializable views of the budget, behind `--format json` and `--format yaml`.
+// Their field names are part of the documented schema : only ever add fields.
+
+/// Balance of a period, in cents.
+#[derive(Debug, Serialize)]
+pub struct Balance {
+    pub period_id: u32,
+    /// Reporting currency the balances are converted into.
+    pub currency: String,
+    /// Money received minus money spent.
+    pub balance: i64,
+    /// Balance once every pending income is received and every estimate is spent.
+    pub end_of_period_estimate: i64,
+}
+
+impl Balance {
+    pub fn new(period_id: u32, incomes: &[Income], expenses: &[Expense]) -> Balance {
+        return Balance {
+            period_id,
+            currency: REPORTING_CURRENCY.clone(),
+            balance: get_balance(incomes, expenses),
+            end_of_period_estimate: get_end_of_period_estimate(incomes, expenses),
+        };
+    }
+}
+
+/// Subtotals of a category, including its sub-categories.
+#[derive(Debug, Serialize)]
+pub struct CategoryReport {
+    pub id: u32,
+    pub parent_id: Option<u32>,
+    pub name: String,
+    pub path: String,
+    pub depth: usize,
+    pub estimate: i64,
+    pub spent: i64,
+}
+
+impl CategoryReport {
+    /// Every category of the tree holding expense lines, in depth-first order.
+    pub fn from_tree(tree: &CategoryTree, expenses: &[Expense]) -> Vec<CategoryReport> {
+        let subtotals = tree.subtotals(expenses);
+
+        return tree.walk()
+            .into_iter()
+            .filter_map(|(category, depth)| {
+                let subtotal = subtotals.get(&category.id)?;
+
+                Some(CategoryReport {
+                    id: category.id,
+                    parent_id: category.parent_id,
+                    name: category.name.clone(),
+                    path: tree.path(category.id),
+                    depth,
+                    estimate: subtotal.estimate,
+                    spent: subtotal.spent,
+                })
+            })
+            .collect();
+    }
+}
+
+/// A period with everything it holds.
+#[derive(Debug, Serialize)]
+pub struct PeriodReport<'a> {
+    pub period: &'a Period,
+    pub incomes: &'a [Income],
+    pub expenses: &'a [Expense],
+    /// Refunds on the expense lines, already taken off their `spent` amount.
+    pub refunds: &'a [Transaction],
+    /// Only with `list --by-category`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<CategoryReport>>,
+    pub currency: String,
+    pub balance: i64,
+    pub end_of_period_estimate: i64,
+}
+
+impl PeriodReport<'_> {
+    /// The lines of a period as recorded, along with its balance in the reporting currency.
+    pub fn new<'a>(period: &'a Period, incomes: &'a [Income], expenses: &'a [Expense], refunds: &'a [Transaction], balance: &Balance) -> PeriodReport<'a> {
+        return PeriodReport {
+            period,
+            incomes,
+            expenses,
+            refunds,
+            categories: None,
+            currency: balance.currency.clone(),
+            balance: balance.balance,
+            end_of_period_estimate: balance.end_of_period_estimate,
+        };
+    }
+}
+
+/// An expense line with its transactions.
+#[derive(Debug, Serialize)]
+pub struct ExpenseReport<'a> {
+    pub expense: &'a Expense,
+    pub transactions: &'a [Transaction],
+}
+
+/// A log line, along with its human-readable description.
+#[derive(Debug, Serialize)]
+pub struct LogReport<'a> {
+    #[serde(flatten)]
+    pub log: &'a Log,
+    pub description: String,
+}
+
+impl LogReport<'_> {
+    pub fn from_logs(logs: &[Log]) -> Vec<LogReport<'_>> {
+        return logs.iter()
+            .map(|log| LogReport { log, description: log.description() })
+            .collect();
+    }
+}
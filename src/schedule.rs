@@ -0,0 +1,99 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+// ------------------------------------------------------------
+// PERIOD SCHEDULE
+// ------------------------------------------------------------
+/// Names accepted for the length of periods.
+pub const PERIOD_LENGTHS: &[&str] = &["manual", "monthly", "biweekly", "weekly", "custom"];
+
+/// How periods follow each other.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// Periods only change on `roll`.
+    Manual,
+    /// A new period starts on this day of every month, or on the last day of shorter months.
+    Monthly { day: u32 },
+    /// A new period starts every `days` days, counted from `anchor` or else from the start of the current period.
+    Every { days: i64, anchor: Option<NaiveDate> },
+}
+
+impl Schedule {
+    /// First boundary strictly after the start of a period, if periods change on their own.
+    pub fn next_boundary(&self, start: NaiveDate) -> Option<NaiveDate> {
+        return match self {
+            Schedule::Manual => None,
+            Schedule::Monthly { day } => {
+                let this_month = day_of_month(start, *day)?;
+
+                match this_month > start {
+                    true => Some(this_month),
+                    false => day_of_month(start.checked_add_months(Months::new(1))?, *day),
+                }
+            },
+            Schedule::Every { days, anchor } => {
+                let anchor = anchor.unwrap_or(start);
+                let count = (start - anchor).num_days().div_euclid(*days) + 1;
+
+                anchor.checked_add_signed(Duration::days(count * days))
+            },
+        };
+    }
+}
+
+/// A day of the month of a date, clamped to the length of that month.
+fn day_of_month(date: NaiveDate, day: u32) -> Option<NaiveDate> {
+    let first = date.with_day(1)?;
+    let last = (first + Months::new(1)).pred_opt()?.day();
+
+    return first.with_day(day.min(last));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    }
+
+    #[test]
+    fn manual_periods_never_end() {
+        assert_eq!(Schedule::Manual.next_boundary(date(2026, 1, 1)), None);
+    }
+
+    #[test]
+    fn monthly_boundaries_follow_the_day() {
+        let schedule = Schedule::Monthly { day: 5 };
+
+        assert_eq!(schedule.next_boundary(date(2026, 10, 1)), Some(date(2026, 10, 5)));
+        assert_eq!(schedule.next_boundary(date(2026, 10, 5)), Some(date(2026, 11, 5)));
+        assert_eq!(schedule.next_boundary(date(2026, 12, 20)), Some(date(2027, 1, 5)));
+    }
+
+    #[test]
+    fn monthly_boundaries_shrink_to_the_end_of_shorter_months() {
+        let schedule = Schedule::Monthly { day: 31 };
+
+        assert_eq!(schedule.next_boundary(date(2026, 1, 31)), Some(date(2026, 2, 28)));
+        assert_eq!(schedule.next_boundary(date(2026, 2, 28)), Some(date(2026, 3, 31)));
+        assert_eq!(schedule.next_boundary(date(2026, 4, 1)), Some(date(2026, 4, 30)));
+        assert_eq!(schedule.next_boundary(date(2028, 1, 31)), Some(date(2028, 2, 29)));
+        assert_eq!(Schedule::Monthly { day: 30 }.next_boundary(date(2026, 2, 1)), Some(date(2026, 2, 28)));
+    }
+
+    #[test]
+    fn fixed_length_boundaries_count_from_the_anchor() {
+        let weekly = Schedule::Every { days: 7, anchor: Some(date(2026, 10, 5)) };
+
+        assert_eq!(weekly.next_boundary(date(2026, 10, 1)), Some(date(2026, 10, 5)));
+        assert_eq!(weekly.next_boundary(date(2026, 10, 5)), Some(date(2026, 10, 12)));
+        assert_eq!(weekly.next_boundary(date(2026, 10, 11)), Some(date(2026, 10, 12)));
+    }
+
+    #[test]
+    fn fixed_length_boundaries_count_from_the_start_without_anchor() {
+        let biweekly = Schedule::Every { days: 14, anchor: None };
+
+        assert_eq!(biweekly.next_boundary(date(2026, 2, 20)), Some(date(2026, 3, 6)));
+    }
+}
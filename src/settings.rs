@@ -0,0 +1,177 @@
+use std::io::Error;
+use std::fs;
+use config::Config;
+use homedir::get_my_home;
+use chrono::NaiveDate;
+use crate::currencies::{code_of, parse_code, precision, symbol, BASE_CURRENCY};
+use crate::schedule::{Schedule, PERIOD_LENGTHS};
+use crate::utils::{MoneyFormat, SymbolPosition, MONEY_PRESETS};
+
+// ------------------------------------------------------------
+// CONFIG
+// ------------------------------------------------------------
+lazy_static!{
+    pub static ref CONFIG: Config = Config::builder()
+        .add_source(config::Environment::with_prefix("EBENEZER").separator("_"))
+        .build()
+        .unwrap();
+
+    /// The configured money format. An invalid configuration falls back to the legacy preset,
+    /// `get_money_format` reports it.
+    pub static ref MONEY_FORMAT: MoneyFormat = get_money_format()
+        .unwrap_or_else(|_| MoneyFormat::preset(MONEY_PRESETS[0], &get_currency()).unwrap());
+
+    /// The configured reporting currency. An invalid configuration falls back to the euro,
+    /// `get_reporting_currency` reports it.
+    pub static ref REPORTING_CURRENCY: String = get_reporting_currency()
+        .unwrap_or_else(|_| BASE_CURRENCY.to_string());
+}
+
+fn prepare_database_dir() -> Result<(), Error> {
+    let path = get_my_home().unwrap().unwrap().as_path()
+    .join("ebenezer");
+
+    if !path.exists() || !path.is_dir() {
+        return fs::create_dir(path);
+    }
+
+    else {
+        return Ok(());
+    }
+}
+
+/// Returns the path to the SQLite DB file, either from the configuration or a reasonable default.
+pub fn get_dbfile() -> String {
+    prepare_database_dir().expect("Unable to create database directory");
+
+    return match CONFIG.get::<String>("dbfile") {
+        Ok(r) => r,
+        _ => {
+           return get_my_home().unwrap().unwrap().as_path()
+            .join("ebenezer")
+            .join("ebenezer.db3")
+            .to_str()
+            .unwrap_or("./ebenezer.db3")
+            .to_string();
+        }
+    }
+}
+
+/// Returns the currency symbol to use, either from the configuration or a reasonable default.
+pub fn get_currency() -> String {
+    return match CONFIG.get::<String>("currency") {
+        Ok(r) => r,
+        _ => "€".to_string()
+    }
+}
+
+/// Returns the code of the currency balances are converted into (`EBENEZER_REPORTING_CURRENCY`),
+/// by default the one of the currency symbol. Amounts are recorded in this currency unless told otherwise.
+pub fn get_reporting_currency() -> crate::Result<String> {
+    return match CONFIG.get::<String>("reporting.currency") {
+        Ok(r) => parse_code(&r).map_err(|_| crate::Error::Config(format!("'{}' is not a valid value for reporting.currency", r))),
+        _ => code_of(&get_currency())
+            .ok_or_else(|| crate::Error::Config(format!("no currency code known for '{}', set reporting.currency to one", get_currency()))),
+    }
+}
+
+/// Symbol to write amounts of a currency with : the configured one for its own currency.
+pub fn currency_symbol(code: &str) -> String {
+    return match code_of(&get_currency()) {
+        Some(c) if c == code => get_currency(),
+        _ => symbol(code).to_string(),
+    }
+}
+
+/// Returns how periods follow each other (`EBENEZER_PERIOD_LENGTH`) : manual by default, periods changing on `roll` only,
+/// monthly on day `EBENEZER_PERIOD_DAY` (1 by default), biweekly, weekly, or custom every `EBENEZER_PERIOD_DAYS` days.
+/// Biweekly, weekly and custom periods are counted from `EBENEZER_PERIOD_START` (YYYY-MM-DD) if set,
+/// or else from the start of the current period.
+pub fn get_period_schedule() -> crate::Result<Schedule> {
+    let invalid = |key: &str, value: &str| crate::Error::Config(format!("'{}' is not a valid value for period.{}", value, key));
+
+    let anchor = match CONFIG.get::<String>("period.start") {
+        Ok(value) => Some(NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| invalid("start", &value))?),
+        _ => None,
+    };
+
+    let length = CONFIG.get::<String>("period.length").unwrap_or(PERIOD_LENGTHS[0].to_string());
+
+    return match length.to_lowercase().as_str() {
+        "manual" => Ok(Schedule::Manual),
+        "monthly" => {
+            let day = match CONFIG.get::<String>("period.day") {
+                Ok(value) => value.parse::<u32>().ok()
+                    .filter(|d| (1..=31).contains(d))
+                    .ok_or_else(|| invalid("day", &value))?,
+                _ => 1,
+            };
+
+            Ok(Schedule::Monthly { day })
+        },
+        "biweekly" => Ok(Schedule::Every { days: 14, anchor }),
+        "weekly" => Ok(Schedule::Every { days: 7, anchor }),
+        "custom" => {
+            let value = CONFIG.get::<String>("period.days")
+                .map_err(|_| crate::Error::Config("custom periods need a number of days in period.days".to_string()))?;
+            let days = value.parse::<i64>().ok()
+                .filter(|d| *d > 0)
+                .ok_or_else(|| invalid("days", &value))?;
+
+            Ok(Schedule::Every { days, anchor })
+        },
+        _ => Err(crate::Error::Config(format!("unknown period length '{}', expected one of {}", length, PERIOD_LENGTHS.join(", ")))),
+    }
+}
+
+/// Returns the money format : a preset (`EBENEZER_MONEY_PRESET`, legacy by default) with optional overrides
+/// of the decimal separator (`EBENEZER_MONEY_DECIMAL`), the thousands separator (`EBENEZER_MONEY_THOUSANDS`,
+/// "none" for no grouping), the symbol position (`EBENEZER_MONEY_POSITION`, prefix or suffix)
+/// and the spacing between symbol and figures (`EBENEZER_MONEY_SPACE`, true or false).
+pub fn get_money_format() -> crate::Result<MoneyFormat> {
+    let invalid = |key: &str, value: &str| crate::Error::Config(format!("'{}' is not a valid value for money.{}", value, key));
+
+    let reporting = get_reporting_currency()?;
+    let preset = CONFIG.get::<String>("money.preset").unwrap_or(MONEY_PRESETS[0].to_string());
+    let mut format = MoneyFormat::preset(&preset.to_lowercase(), &currency_symbol(&reporting))
+        .ok_or_else(|| crate::Error::Config(format!("unknown money preset '{}', expected one of {}", preset, MONEY_PRESETS.join(", "))))?;
+
+    format.precision = precision(&reporting);
+
+    if let Ok(value) = CONFIG.get::<String>("money.decimal") {
+        format.decimal_separator = single_char(&value).ok_or_else(|| invalid("decimal", &value))?;
+    }
+
+    if let Ok(value) = CONFIG.get::<String>("money.thousands") {
+        format.thousands_separator = match value.as_str() {
+            "none" => None,
+            _ => Some(single_char(&value).ok_or_else(|| invalid("thousands", &value))?),
+        };
+    }
+
+    if let Ok(value) = CONFIG.get::<String>("money.position") {
+        format.position = match value.to_lowercase().as_str() {
+            "prefix" => SymbolPosition::Prefix,
+            "suffix" => SymbolPosition::Suffix,
+            _ => return Err(invalid("position", &value)),
+        };
+    }
+
+    if let Ok(value) = CONFIG.get::<String>("money.space") {
+        format.spaced = value.parse().map_err(|_| invalid("space", &value))?;
+    }
+
+    if format.thousands_separator == Some(format.decimal_separator) {
+        return Err(crate::Error::Config("the decimal and thousands separators must differ".to_string()));
+    }
+
+    return Ok(format);
+}
+
+fn single_char(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    return match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    };
+}
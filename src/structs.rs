@@ -1,75 +1,162 @@
 use std::fmt;
-use crate::utils::print_in_currency;
+use std::str::FromStr;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use serde::Serialize;
+use crate::utils::print_amount;
 
 // ------------------------------------------------------------
 // STRUCTS
 // ------------------------------------------------------------
-use chrono::NaiveDateTime;
+use chrono::{Local, NaiveDate, NaiveDateTime};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Period {
     pub id: u32,
-    pub start_date: String,
-    pub end_date: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    /// Optional name, such as "October 2026".
+    pub name: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Income {
-    pub _id: u32,
+    pub id: u32,
+    pub period_id: u32,
     pub label: String,
-    pub value: i64, // in cents.
+    pub value: i64, // in cents, expected amount.
+    pub received: i64, // in cents.
+    pub expected_date: Option<NaiveDate>,
+    pub recurring: bool,
+    /// ISO 4217 code of the currency of the amounts.
+    pub currency: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Expense {
     pub id: u32,
+    pub period_id: u32,
     pub label: String,
     pub estimate: i64, // in cents.
-    pub spent: i64, // in cents.
-    pub expense_type: ExpenseType
+    pub spent: i64, // in cents, sum of the transactions converted into the line's currency.
+    pub refunded: i64, // in cents, sum of the refunds, already taken off `spent`.
+    #[serde(rename = "type")]
+    pub expense_type: ExpenseType,
+    pub category_id: Option<u32>,
+    /// ISO 4217 code of the currency of the amounts.
+    pub currency: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Category {
+    pub id: u32,
+    pub parent_id: Option<u32>,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Transaction {
+    pub id: u32,
+    pub expense_id: u32,
+    pub amount: i64, // in cents.
+    pub date: NaiveDate,
+    pub memo: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// Money given back : the amount is negative.
+    pub refund: bool,
+    /// ISO 4217 code of the currency of the amount.
+    pub currency: String,
+    pub line_amount: i64, // in cents of the expense line's currency, converted when the transaction was recorded.
 }
 
-#[derive(Debug)]
+/// Units of a currency worth one unit of the base currency on a date.
+#[derive(Debug, Serialize)]
+pub struct ExchangeRate {
+    pub date: NaiveDate,
+    pub currency: String,
+    pub rate: f64,
+}
+
+#[derive(Debug, Serialize)]
 pub struct Log {
     pub id: u32,
     pub timer: NaiveDateTime,
     pub action: String,
     pub arg1: Option<String>,
     pub arg2: Option<String>,
-    pub arg3: Option<String>
+    pub arg3: Option<String>,
+    pub undone: bool,
+    /// Period the command changed.
+    pub period_id: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub enum ExpenseType {
     FIXED,
     ESTIMATED,
     UNPLANNED
 }
 
+impl Period {
+    /// Whether a date can be recorded in the period. An ongoing period ends today, so nothing is recorded in the future.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let end = self.end_date.unwrap_or_else(|| Local::now().date_naive());
+        return self.start_date <= date && date <= end;
+    }
+}
+
 impl fmt::Display for Period {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.name.as_ref().map(|n| format!(" ({})", n)).unwrap_or_default();
+
         match& self.end_date {
             Some(d) => {
-                write!(f, "Period {}, started on {}, ended on {}.", 
+                write!(f, "Period {}{}, started on {}, ended on {}.", 
                 self.id, 
+                name,
                 self.start_date,
                 d)
             },
             None => {
-                write!(f, "Period {}, started on {}, ongoing.", 
+                write!(f, "Period {}{}, started on {}, ongoing.", 
                 self.id, 
+                name,
                 self.start_date)
             }
         }
     }
 }
 
+impl Income {
+    /// Amount still expected to come in.
+    pub fn pending(&self) -> i64 {
+        return (self.value - self.received).max(0);
+    }
+}
+
 impl fmt::Display for Income {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} : {}", 
-            self.label, 
-            print_in_currency(self.value)
-    )}
+        if self.pending() == 0 {
+            write!(f, "{} : {}",
+                self.label,
+                print_amount(self.received, &self.currency))?;
+        }
+
+        else {
+            write!(f, "{} : {} received out of {}",
+                self.label,
+                print_amount(self.received, &self.currency),
+                print_amount(self.value, &self.currency))?;
+
+            if let Some(d) = self.expected_date {
+                write!(f, ", expected on {}", d)?;
+            }
+        }
+
+        match self.recurring {
+            true => write!(f, " (recurring)"),
+            false => Ok(())
+        }
+    }
 }
 
 impl fmt::Display for ExpenseType {
@@ -82,32 +169,94 @@ impl fmt::Display for ExpenseType {
     }
 }
 
+impl FromStr for ExpenseType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "FIXED" => Ok(ExpenseType::FIXED),
+            "ESTIMATED" => Ok(ExpenseType::ESTIMATED),
+            "UNPLANNED" => Ok(ExpenseType::UNPLANNED),
+            _ => Err(format!("Unknown expense type : {}", s))
+        }
+    }
+}
+
+impl FromSql for ExpenseType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        return value.as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()));
+    }
+}
+
 impl fmt::Display for Expense {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} : {} spent out of {}", 
             self.label, 
-            print_in_currency(self.spent), 
-            print_in_currency(self.estimate)
-    )}
+            print_amount(self.spent, &self.currency), 
+            print_amount(self.estimate, &self.currency))?;
+
+        match self.refunded {
+            0 => Ok(()),
+            refunded => write!(f, " ({} refunded)", print_amount(refunded, &self.currency))
+        }
+    }
 }
 
-impl fmt::Display for Log {
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.refund {
+            true => write!(f, "#{} - {} : refund of {}",
+                self.id,
+                self.date,
+                print_amount(-self.amount, &self.currency))?,
+            false => write!(f, "#{} - {} : {}",
+                self.id,
+                self.date,
+                print_amount(self.amount, &self.currency))?,
+        }
+
+        match &self.memo {
+            Some(memo) => write!(f, " ({})", memo),
+            None => Ok(())
+        }
+    }
+}
+
+impl fmt::Display for ExchangeRate {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let bare = get_action_label(&self);
+        write!(f, "{} : {} (on {})", self.currency, self.rate, self.date)
+    }
+}
 
-        let arg1 = &(self.arg1.to_owned().unwrap_or("".to_string()));
-        let arg2 = &(self.arg2.to_owned().unwrap_or("".to_string()));
-        let arg3 = &(self.arg3.to_owned().unwrap_or("".to_string()));
+impl Log {
+    /// The log message, with its parameters filled in.
+    pub fn description(&self) -> String {
+        let bare = get_action_label(self);
 
-        let res = bare
-            .replace("%1", &arg1)
-            .replace("%2", &arg2)
-            .replace("%3", &arg3);
+        let arg1 = self.arg1.as_deref().unwrap_or("");
+        let arg2 = self.arg2.as_deref().unwrap_or("");
+        let arg3 = self.arg3.as_deref().unwrap_or("");
 
-        write!(f, "{} - {} : {}", 
-            self.id.to_string(), 
-            self.timer.format("%Y-%m-%d %H:%M:%S").to_string(), 
-            res)
+        return bare
+            .replace("%1", arg1)
+            .replace("%2", arg2)
+            .replace("%3", arg3);
+    }
+}
+
+impl fmt::Display for Log {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {} : {}",
+            self.id,
+            self.timer.format("%Y-%m-%d %H:%M:%S"),
+            self.description())?;
+
+        match self.undone {
+            true => write!(f, " (undone)"),
+            false => Ok(())
+        }
     }
 }
 
@@ -116,12 +265,37 @@ fn get_action_label(log: &Log) -> &str {
         "START_PERIOD" => "Started a new period. (#%1)",
         "END_PERIOD" => "Ended period #%1.",
         "ADD_INCOME" => "Added income of %2 : %1.",
+        "EXPECT_INCOME" => "Expecting income of %2 : %1.%3",
+        "RECEIVE_INCOME" => "Received %2 of income %1.",
+        "SET_RECURRING" => "Income %1 now recurs every period.",
+        "STOP_RECURRING" => "Income %1 no longer recurs.",
+        "UPDATE_INCOME" => "Updated income %1 : from %2 to %3.",
+        "RENAME_INCOME" => "Renamed income %1 : now labelled %2.",
+        "REMOVE_INCOME" => "Removed income %1 (%2).",
         "ADD_EXPENSE" => "Added expense : %1 : estimated %2, spent %3.",
         "UPDATE_ESTIMATE" => "Updated expense %1 : new estimate of %2.",
         "RENAME_ESTIMATE" => "Renamed expense %1 : now labelled %2.",
         "REMOVE_EXPENSE" => "Removed expense %1.",
         "SPEND" => "Spent %2 on %1.",
         "OVERRIDE_SPENDING" => "Set spending of %2 on %1.",
+        "REFUND" => "Refunded %2 on %1.",
+        "IMPORT_RATES" => "Imported %2 exchange rates from %1.",
+        "AMEND_TRANSACTION" => "Amended transaction %1 : %2 -> %3.",
+        "DELETE_TRANSACTION" => "Deleted transaction %1 : %2.",
+        "SET_CATEGORY" => "Filed expense %1 under %2.",
+        "CLEAR_CATEGORY" => "Removed expense %1 from category %2.",
+        "IMPORT" => "Imported %2 entries from %1.",
+        "IMPORT_WORKSHEET" => "Imported %2 periods from %1.",
+        "ROLL" => "Rolled over from period #%1 to period #%2.",
+        "AUTO_ROLL" => "Rolled over from period #%1 to period #%2, starting on %3.",
+        "CREATE_PERIOD" => "Created period #%1 : %2.",
+        "NAME_PERIOD" => "Named period #%1 %2.",
+        "UNNAME_PERIOD" => "Removed the name of period #%1.",
+        "PERIOD_DATES" => "Moved period #%1 to %2.",
+        "REOPEN_PERIOD" => "Reopened period #%1.",
+        "DELETE_PERIOD" => "Deleted period #%1 (%2).",
+        "UNDO" => "Undid #%1 : %2",
+        "REDO" => "Redid #%1 : %2",
         _ => ""
     }
 }
@@ -1,33 +1,328 @@
+use std::fmt;
+
 // ------------------------------------------------------------
 // UTILS
 // ------------------------------------------------------------
-/// Parse a string (hopefully numerical) into an amount in cents.
-pub fn parse_into_cents(value: &str) -> i64 {
-    let splitted: Vec<&str> = value.split(&['.', ','][..]).collect();
 
-    if splitted.len() > 2 {
-        panic!("Amounts shouldn't have more than one decimal separator. ({})", value);
+/// Why a string is not an amount.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AmountError {
+    Empty,
+    InvalidCharacter(char),
+    SeveralDecimalSeparators,
+    TooManyDecimals,
+    InvalidGrouping,
+    TooLarge,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::Empty => write!(f, "there is no figure"),
+            AmountError::InvalidCharacter(c) => write!(f, "unexpected character '{}'", c),
+            AmountError::SeveralDecimalSeparators => write!(f, "more than one decimal separator"),
+            AmountError::TooManyDecimals => write!(f, "more digits after the decimal separator than the currency has"),
+            AmountError::InvalidGrouping => write!(f, "thousands should be grouped by three digits"),
+            AmountError::TooLarge => write!(f, "the amount is too large"),
+        }
+    }
+}
+
+/// Currency symbols ignored around amounts, on top of the configured one.
+const CURRENCY_SYMBOLS: &[&str] = &["€", "$", "£", "¥"];
+
+/// Characters grouping thousands whatever the money format : spaces and apostrophes.
+const GROUPING_CHARACTERS: &[char] = &[' ', '\u{a0}', '\u{202f}', '\''];
+
+/// Parse an amount typed by the user into cents of the reporting currency, following the configured money format.
+/// Accepts a sign, thousands separators and a currency symbol, e.g. "-1 234,56 €" or "€1,234.56".
+pub fn parse_into_cents(value: &str) -> crate::Result<i64> {
+    return parse_in_currency(value, &crate::settings::REPORTING_CURRENCY);
+}
+
+/// Same as `parse_into_cents`, for an amount in the smallest unit of the given currency.
+/// The currency code may stand in for its symbol, e.g. "12.50 USD".
+pub fn parse_in_currency(value: &str, currency: &str) -> crate::Result<i64> {
+    let format = crate::settings::MONEY_FORMAT.in_currency(currency);
+    let symbols = [format.symbol.as_str(), currency];
+
+    return parse_amount(value, format.decimal_separator, format.thousands_separator, &symbols, format.precision)
+        .map_err(|reason| crate::Error::InvalidAmount(value.to_string(), reason));
+}
+
+/// Parse an amount into its smallest unit, given the number of decimals of its currency.
+/// When '.' and ',' both appear, the last one is the decimal separator.
+/// When only one of them appears, it is the decimal separator unless it is `thousands_separator`
+/// followed by three digits, or appears several times.
+pub fn parse_amount(value: &str, decimal_separator: char, thousands_separator: Option<char>, symbols: &[&str], precision: u32) -> Result<i64, AmountError> {
+    let symbols: Vec<&str> = CURRENCY_SYMBOLS.iter().chain(symbols).copied().filter(|s| !s.is_empty()).collect();
+
+    let unsigned = strip_symbols(value, &symbols);
+    let (negative, unsigned) = match unsigned.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, unsigned.strip_prefix('+').unwrap_or(unsigned)),
+    };
+    let figures = strip_symbols(unsigned, &symbols);
+
+    if figures.is_empty() {
+        return Err(AmountError::Empty);
+    }
+
+    let last_mark = figures.rfind(['.', ',']);
+
+    // Pick the decimal mark, if any.
+    let decimal_position = match last_mark {
+        None => None,
+        Some(position) => {
+            let mark = figures[position..].chars().next().unwrap_or(decimal_separator);
+            let other = if mark == '.' { ',' } else { '.' };
+            let occurrences = figures.matches(mark).count();
+            let decimals = figures.len() - position - 1;
+
+            if figures.contains(other) || mark == decimal_separator {
+                if occurrences > 1 {
+                    return Err(AmountError::SeveralDecimalSeparators);
+                }
+
+                Some(position)
+            }
+            else if occurrences == 1 && !(thousands_separator == Some(mark) && decimals == 3) {
+                Some(position)
+            }
+            else {
+                None
+            }
+        }
+    };
+
+    let (units, cents) = match decimal_position {
+        Some(position) => (&figures[..position], &figures[position + 1..]),
+        None => (figures, ""),
+    };
+
+    if let Some(c) = cents.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(AmountError::InvalidCharacter(c));
+    }
+
+    if cents.len() > precision as usize {
+        return Err(AmountError::TooManyDecimals);
+    }
+
+    let is_grouping = |c: char| GROUPING_CHARACTERS.contains(&c) || c == '.' || c == ',' || Some(c) == thousands_separator;
+
+    if let Some(c) = units.chars().find(|c| !c.is_ascii_digit() && !is_grouping(*c)) {
+        return Err(AmountError::InvalidCharacter(c));
     }
 
-    let main:i64 = splitted[0].parse()
-        .expect("Input isn't a valid amount !");
+    let groups: Vec<&str> = units.split(is_grouping).collect();
 
-    let str_cents = splitted.get(1).or(Some(&"0"))
-        .expect("Default value 0 should always be present.");
+    if groups.len() > 1 && (groups[0].is_empty() || groups[0].len() > 3 || groups[1..].iter().any(|g| g.len() != 3)) {
+        return Err(AmountError::InvalidGrouping);
+    }
 
-    if str_cents.len() > 2 {
-        panic!("More than two digits after decimal place. ({})", value)
+    let digits: String = groups.concat();
+
+    if digits.is_empty() && cents.is_empty() {
+        return Err(AmountError::Empty);
     }
 
-    let padded_cents = format!("{:0<2}", str_cents);
+    let units: i64 = if digits.is_empty() { 0 } else { digits.parse().map_err(|_| AmountError::TooLarge)? };
+    let cents: i64 = format!("{:0<width$}", cents, width = precision as usize).parse().unwrap_or(0);
+
+    let amount = units.checked_mul(10i64.pow(precision))
+        .and_then(|u| u.checked_add(cents))
+        .ok_or(AmountError::TooLarge)?;
 
-    let cents: i64 = padded_cents.parse().expect("Input isn't a valid amount !");
-    return main * 100 + cents;
+    return Ok(if negative { -amount } else { amount });
 }
 
+/// Remove a currency symbol standing before or after the figures.
+fn strip_symbols<'a>(value: &'a str, symbols: &[&str]) -> &'a str {
+    let mut res = value.trim();
+
+    for symbol in symbols {
+        res = res.strip_prefix(symbol).unwrap_or(res).trim();
+        res = res.strip_suffix(symbol).unwrap_or(res).trim();
+    }
+
+    return res;
+}
+
+/// Print an amount in cents of the reporting currency with the configured money format, e.g. "1234,50€".
 pub fn print_in_currency(amount: i64) -> String {
-    let currency = crate::get_currency();
-    let cents = amount % 100;
-    let money = amount / 100;
-    return format!("{},{:02}{}", money, cents, currency);
+    return crate::settings::MONEY_FORMAT.format(amount);
+}
+
+/// Print an amount in the smallest unit of a currency with the configured money format, e.g. "1234¥".
+pub fn print_amount(amount: i64, currency: &str) -> String {
+    return crate::settings::MONEY_FORMAT.in_currency(currency).format(amount);
+}
+
+/// Where the currency symbol goes, relative to the figures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolPosition {
+    Prefix,
+    Suffix,
+}
+
+/// How amounts are written : separators, currency symbol and its position.
+#[derive(Debug, Clone)]
+pub struct MoneyFormat {
+    pub decimal_separator: char,
+    /// Groups thousands when set, e.g. '.' for "1.234,50".
+    pub thousands_separator: Option<char>,
+    pub symbol: String,
+    /// Number of decimals of the currency.
+    pub precision: u32,
+    pub position: SymbolPosition,
+    /// Puts a space between the symbol and the figures.
+    pub spaced: bool,
+}
+
+/// Names of the presets, the first one being the default.
+pub const MONEY_PRESETS: &[&str] = &["legacy", "fr", "de", "en"];
+
+impl MoneyFormat {
+    /// A preset format with the given symbol :
+    /// legacy ("-1234,50€", the historical output), fr ("-1 234,50 €"), de ("-1.234,50 €") or en ("-€1,234.50").
+    pub fn preset(name: &str, symbol: &str) -> Option<MoneyFormat> {
+        let (decimal_separator, thousands_separator, position, spaced) = match name {
+            "legacy" => (',', None, SymbolPosition::Suffix, false),
+            "fr" => (',', Some(' '), SymbolPosition::Suffix, true),
+            "de" => (',', Some('.'), SymbolPosition::Suffix, true),
+            "en" => ('.', Some(','), SymbolPosition::Prefix, false),
+            _ => return None,
+        };
+
+        return Some(MoneyFormat { decimal_separator, thousands_separator, symbol: symbol.to_string(), precision: 2, position, spaced });
+    }
+
+    /// The same format, for amounts in another currency.
+    pub fn in_currency(&self, currency: &str) -> MoneyFormat {
+        return MoneyFormat {
+            symbol: crate::settings::currency_symbol(currency),
+            precision: crate::currencies::precision(currency),
+            ..self.clone()
+        };
+    }
+
+    pub fn format(&self, amount: i64) -> String {
+        let divisor = 10u64.pow(self.precision);
+        let units = (amount.unsigned_abs() / divisor).to_string();
+        let cents = amount.unsigned_abs() % divisor;
+
+        let units = match self.thousands_separator {
+            Some(separator) => group_thousands(&units, separator),
+            None => units,
+        };
+
+        let sign = if amount < 0 { "-" } else { "" };
+        let space = if self.spaced { " " } else { "" };
+        let figures = match self.precision {
+            0 => units,
+            precision => format!("{}{}{:0width$}", units, self.decimal_separator, cents, width = precision as usize),
+        };
+
+        return match self.position {
+            SymbolPosition::Prefix => format!("{}{}{}{}", sign, self.symbol, space, figures),
+            SymbolPosition::Suffix => format!("{}{}{}{}", sign, figures, space, self.symbol),
+        };
+    }
+}
+
+/// Insert a separator every three digits, from the right.
+fn group_thousands(digits: &str, separator: char) -> String {
+    let mut res = String::new();
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            res.push(separator);
+        }
+
+        res.push(digit);
+    }
+
+    return res;
+}
+
+/// Print an amount in the smallest unit of a currency as a plain decimal number, e.g. "-12.50",
+/// for files read by other programs.
+pub fn print_decimal(amount: i64, currency: &str) -> String {
+    let precision = crate::currencies::precision(currency);
+    let divisor = 10u64.pow(precision);
+    let sign = if amount < 0 { "-" } else { "" };
+
+    return match precision {
+        0 => format!("{}{}", sign, amount.unsigned_abs()),
+        _ => format!("{}{}.{:0width$}", sign, amount.unsigned_abs() / divisor, amount.unsigned_abs() % divisor, width = precision as usize),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy(value: &str) -> Result<i64, AmountError> {
+        return parse_amount(value, ',', None, &["€"], 2);
+    }
+
+    fn with_preset(preset: &str, value: &str) -> Result<i64, AmountError> {
+        let format = MoneyFormat::preset(preset, "€").unwrap();
+        return parse_amount(value, format.decimal_separator, format.thousands_separator, &[&format.symbol], format.precision);
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_amount() {
+        assert_eq!(legacy("abc"), Err(AmountError::InvalidCharacter('a')));
+        assert_eq!(legacy(""), Err(AmountError::Empty));
+        assert_eq!(legacy("€"), Err(AmountError::Empty));
+        assert_eq!(legacy("1.2.3"), Err(AmountError::InvalidGrouping));
+        assert_eq!(legacy("1,2,3"), Err(AmountError::SeveralDecimalSeparators));
+        assert_eq!(legacy("12,345"), Err(AmountError::TooManyDecimals));
+        assert_eq!(legacy("99999999999999999999"), Err(AmountError::TooLarge));
+    }
+
+    #[test]
+    fn reads_negative_amounts() {
+        assert_eq!(legacy("-0,50"), Ok(-50));
+        assert_eq!(legacy("-12,05"), Ok(-1205));
+        assert_eq!(legacy("+3"), Ok(300));
+    }
+
+    #[test]
+    fn reads_thousands_separators() {
+        assert_eq!(legacy("1 234,56"), Ok(123456));
+        assert_eq!(legacy("1.234,56"), Ok(123456));
+        assert_eq!(with_preset("fr", "1 234,56"), Ok(123456));
+        assert_eq!(with_preset("de", "1.234,56"), Ok(123456));
+        assert_eq!(with_preset("de", "1.234"), Ok(123400));
+        assert_eq!(with_preset("en", "1,234.56"), Ok(123456));
+        assert_eq!(with_preset("en", "1,234"), Ok(123400));
+        assert_eq!(legacy("12 34,56"), Err(AmountError::InvalidGrouping));
+    }
+
+    #[test]
+    fn reads_currency_symbols() {
+        assert_eq!(legacy("12,50€"), Ok(1250));
+        assert_eq!(legacy("12,50 €"), Ok(1250));
+        assert_eq!(legacy("-12,50€"), Ok(-1250));
+        assert_eq!(with_preset("en", "€1,234.50"), Ok(123450));
+        assert_eq!(with_preset("en", "-€0.50"), Ok(-50));
+        assert_eq!(parse_amount("12.50 USD", '.', Some(','), &["$", "USD"], 2), Ok(1250));
+    }
+
+    /// Without a thousands separator, "1.234" is taken as a decimal amount and refused,
+    /// rather than silently read as 1234 : "12.5" is a decimal amount under the same preset.
+    #[test]
+    fn legacy_refuses_a_dot_followed_by_three_digits() {
+        assert_eq!(legacy("12.5"), Ok(1250));
+        assert_eq!(legacy("1.234"), Err(AmountError::TooManyDecimals));
+    }
+
+    #[test]
+    fn follows_the_precision_of_the_currency() {
+        assert_eq!(parse_amount("1500", ',', None, &[], 0), Ok(1500));
+        assert_eq!(parse_amount("1,5", ',', None, &[], 0), Err(AmountError::TooManyDecimals));
+        assert_eq!(parse_amount("1,234", ',', None, &[], 3), Ok(1234));
+    }
 }
@@ -0,0 +1,137 @@
+#![allow(clippy::needless_return)]
+
+use chrono::NaiveDate;
+use ebenezer::Ledger;
+use ebenezer::export::PeriodData;
+use ebenezer::export::beancount::write_beancount;
+use ebenezer::export::journal::write_journal;
+use ebenezer::import::journal::read_journal;
+use ebenezer::structs::ExpenseType;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    return NaiveDate::from_ymd_opt(y, m, d).unwrap();
+}
+
+/// A closed period with a received income, an expected one, a fixed line and purchases in two currencies.
+fn small_ledger() -> Vec<PeriodData> {
+    let ledger = Ledger::open(":memory:").unwrap();
+    let period = ledger.create_period(date(2026, 1, 1), Some(date(2026, 1, 31)), Some("January")).unwrap();
+
+    ledger.create_income(period, "Salary", 200000, "EUR", false).unwrap();
+    ledger.expect_income(period, "Invoice", 50000, "EUR", Some(date(2026, 1, 20)), false).unwrap();
+    ledger.receive_income(&ledger.get_income(period, "Invoice").unwrap(), 30000).unwrap();
+    ledger.expect_income(period, "Bonus", 10000, "EUR", None, false).unwrap();
+
+    ledger.create_expense(period, "Rent", ExpenseType::FIXED, 80000, 0, "EUR").unwrap();
+    ledger.spend(period, "Rent", 80000, "EUR", Some(date(2026, 1, 5)), None).unwrap();
+    ledger.spend(period, "Food; market", 1234, "EUR", Some(date(2026, 1, 10)), Some("weekly \"bio\" basket")).unwrap();
+    ledger.spend(period, "trip", 5000, "USD", Some(date(2026, 1, 12)), None).unwrap();
+
+    return vec![ledger.get_period_data(ledger.get_period(period).unwrap()).unwrap()];
+}
+
+fn export(write: impl FnOnce(&mut Vec<u8>)) -> String {
+    let mut buffer = Vec::new();
+    write(&mut buffer);
+    return String::from_utf8(buffer).unwrap();
+}
+
+#[test]
+fn journal_export_writes_ledger_syntax() {
+    let periods = small_ledger();
+    let text = export(|buffer| write_journal(buffer, &periods).unwrap());
+
+    assert_eq!(text, r#"; Exported from Ebenezer
+commodity 1000.00 €
+commodity 1000.00 $
+
+account assets:budget
+account expenses:fixed:rent
+account expenses:unplanned:food market
+account expenses:unplanned:trip
+account income:bonus
+account income:invoice
+account income:salary
+
+; Period 2 : 2026-01-01 -> 2026-01-31
+
+2026-01-01 Salary
+    assets:budget  2000.00 €
+    income:salary
+
+2026-01-20 Invoice
+    assets:budget  300.00 €
+    income:invoice
+
+2026-01-05 Rent
+    expenses:fixed:rent  800.00 €
+    assets:budget
+
+2026-01-10 Food, market  ; weekly "bio" basket
+    expenses:unplanned:food market  12.34 €
+    assets:budget
+
+2026-01-12 trip
+    expenses:unplanned:trip  50.00 $
+    assets:budget
+"#);
+}
+
+#[test]
+fn journal_export_reads_back_with_the_journal_import() {
+    let periods = small_ledger();
+    let path = std::env::temp_dir().join(format!("ebenezer-export-{}.journal", std::process::id()));
+
+    write_journal(std::fs::File::create(&path).unwrap(), &periods).unwrap();
+    let postings = read_journal(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let read: Vec<(NaiveDate, &str, i64)> = postings.iter().map(|p| (p.date, p.account.as_str(), p.amount)).collect();
+    assert_eq!(read, vec![
+        (date(2026, 1, 1), "income:salary", 200000),
+        (date(2026, 1, 20), "income:invoice", 30000),
+        (date(2026, 1, 5), "expenses:fixed:rent", 80000),
+        (date(2026, 1, 10), "expenses:unplanned:food market", 1234),
+        (date(2026, 1, 12), "expenses:unplanned:trip", 5000),
+    ]);
+}
+
+#[test]
+fn beancount_export_writes_beancount_syntax() {
+    let periods = small_ledger();
+    let text = export(|buffer| write_beancount(buffer, &periods, "EUR").unwrap());
+
+    assert_eq!(text, r#"; Exported from Ebenezer
+option "operating_currency" "EUR"
+
+2026-01-01 open Assets:Budget EUR,USD
+2026-01-01 open Expenses:Fixed:Rent EUR,USD
+2026-01-01 open Expenses:Unplanned:Food-Market EUR,USD
+2026-01-01 open Expenses:Unplanned:Trip EUR,USD
+2026-01-01 open Income:Bonus EUR,USD
+2026-01-01 open Income:Invoice EUR,USD
+2026-01-01 open Income:Salary EUR,USD
+
+; Period 2 : 2026-01-01 -> 2026-01-31
+
+2026-01-01 * "Salary"
+  Assets:Budget  2000.00 EUR
+  Income:Salary
+
+2026-01-20 * "Invoice"
+  Assets:Budget  300.00 EUR
+  Income:Invoice
+
+2026-01-05 * "Rent"
+  Expenses:Fixed:Rent  800.00 EUR
+  Assets:Budget
+
+2026-01-10 * "Food; market" "weekly \"bio\" basket"
+  Expenses:Unplanned:Food-Market  12.34 EUR
+  Assets:Budget
+
+2026-01-12 * "trip"
+  Expenses:Unplanned:Trip  50.00 USD
+  Assets:Budget
+"#);
+}
@@ -0,0 +1,263 @@
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use chrono::NaiveDate;
+use ebenezer::Ledger;
+use ebenezer::import::{camt, csv, mt940, ofx, qif, LabelRules, StatementEntry};
+use ebenezer::import::csv::CsvMapping;
+use ebenezer::import::journal::read_journal;
+use ebenezer::import::worksheet::{read_worksheet, WorksheetPeriod};
+
+fn fixture(name: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name);
+}
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    return NaiveDate::from_ymd_opt(y, m, d).unwrap();
+}
+
+fn summary(entries: &[StatementEntry]) -> Vec<(NaiveDate, i64, &str)> {
+    return entries.iter().map(|e| (e.date, e.amount, e.description.as_str())).collect();
+}
+
+fn assert_distinct_references(entries: &[StatementEntry]) {
+    let mut references: Vec<&str> = entries.iter().map(|e| e.reference.as_str()).collect();
+    references.sort();
+    references.dedup();
+    assert_eq!(references.len(), entries.len());
+}
+
+// ----- CSV -----
+fn french_mapping() -> CsvMapping {
+    return CsvMapping {
+        date_column: "Date".to_string(),
+        amount_column: "amount".to_string(),
+        description_column: "Label".to_string(),
+        date_format: "%d/%m/%Y".to_string(),
+        ..CsvMapping::default()
+    };
+}
+
+#[test]
+fn csv_reads_dates_signs_and_separators() {
+    let entries = csv::read_statement(fixture("statement.csv"), &french_mapping()).unwrap();
+
+    assert_eq!(summary(&entries), vec![
+        (date(2026, 10, 5), -1250, "LIDL; Paris"),
+        (date(2026, 10, 6), -123456, "Rent"),
+        (date(2026, 10, 7), -320, "Coffee"),
+        (date(2026, 10, 7), -320, "Coffee"),
+        (date(2026, 10, 8), 200000, "Salary"),
+    ]);
+
+    let us = CsvMapping { decimal_separator: '.', delimiter: b',', ..CsvMapping::default() };
+    let entries = csv::read_statement(fixture("statement_us.csv"), &us).unwrap();
+
+    assert_eq!(summary(&entries), vec![
+        (date(2026, 10, 5), -320, "Coffee"),
+        (date(2026, 10, 6), -123456, "Rent"),
+    ]);
+}
+
+#[test]
+fn csv_reads_columns_by_position_and_inverted_amounts() {
+    let mapping = CsvMapping {
+        date_column: "1".to_string(),
+        amount_column: "3".to_string(),
+        description_column: "2".to_string(),
+        inverted: true,
+        ..french_mapping()
+    };
+    let entries = csv::read_statement(fixture("statement.csv"), &mapping).unwrap();
+
+    assert_eq!(entries[0].amount, 1250);
+    assert_eq!(entries[4].amount, -200000);
+}
+
+#[test]
+fn csv_references_duplicates_apart() {
+    let entries = csv::read_statement(fixture("statement.csv"), &french_mapping()).unwrap();
+
+    assert_distinct_references(&entries);
+    assert_eq!(entries[2].reference, "csv:2026-10-07|-320|Coffee#1");
+    assert_eq!(entries[3].reference, "csv:2026-10-07|-320|Coffee#2");
+}
+
+// ----- OFX -----
+#[test]
+fn ofx_reads_dates_signs_and_separators() {
+    let entries = ofx::read_statement(fixture("statement.ofx")).unwrap();
+
+    assert_eq!(summary(&entries), vec![
+        (date(2026, 10, 5), -1250, "LIDL & CO"),
+        (date(2026, 10, 6), -10000, "Savings"),
+        (date(2026, 10, 1), 200000, "SALAIRE"),
+    ]);
+}
+
+#[test]
+fn ofx_scopes_references_by_statement_account() {
+    let entries = ofx::read_statement(fixture("statement.ofx")).unwrap();
+    let references: Vec<&str> = entries.iter().map(|e| e.reference.as_str()).collect();
+
+    // The account of a transfer's recipient does not replace the one of the statement.
+    assert_eq!(references, vec!["ofx:11111:A1", "ofx:11111:A2", "ofx:22222:A1"]);
+}
+
+// ----- QIF -----
+#[test]
+fn qif_reads_dates_signs_and_separators() {
+    let entries = qif::read_statement(fixture("statement.qif"), "%m/%d/%Y").unwrap();
+
+    assert_eq!(summary(&entries), vec![
+        (date(2026, 10, 5), -450, "Coffee"),
+        (date(2026, 10, 5), -450, "Coffee"),
+        (date(2026, 10, 6), -123456, "Rent"),
+        (date(2026, 10, 7), 200000, "Payroll"),
+    ]);
+}
+
+#[test]
+fn qif_references_duplicates_apart() {
+    let entries = qif::read_statement(fixture("statement.qif"), "%m/%d/%Y").unwrap();
+
+    assert_distinct_references(&entries);
+    assert_eq!(entries[2].reference, qif::read_statement(fixture("statement.qif"), "%m/%d/%Y").unwrap()[2].reference);
+}
+
+#[test]
+fn qif_reads_two_digit_years() {
+    let entries = qif::read_statement(fixture("two_digit_year.qif"), "%d/%m/%Y").unwrap();
+
+    let dates: Vec<NaiveDate> = entries.iter().map(|e| e.date).collect();
+    assert_eq!(dates, vec![date(2026, 10, 18), date(2026, 10, 5), date(2026, 10, 1)]);
+}
+
+// ----- CAMT.053 -----
+#[test]
+fn camt_reads_booked_entries() {
+    let entries = camt::read_statement(fixture("statement.xml")).unwrap();
+
+    assert_eq!(summary(&entries), vec![
+        (date(2026, 10, 5), -1250, "LIDL"),
+        (date(2026, 10, 1), 200000, "ACME"),
+        (date(2026, 10, 7), -320, "Bakery"),
+        (date(2026, 10, 7), -320, "Bakery"),
+    ]);
+}
+
+#[test]
+fn camt_references_duplicates_apart() {
+    let entries = camt::read_statement(fixture("statement.xml")).unwrap();
+
+    assert_distinct_references(&entries);
+    assert_eq!(entries[0].reference, "camt:FR7630004000031234567890143:E1#1");
+}
+
+// ----- MT940 -----
+#[test]
+fn mt940_reads_dates_signs_and_separators() {
+    let entries = mt940::read_statement(fixture("statement.sta")).unwrap();
+
+    assert_eq!(summary(&entries), vec![
+        (date(2026, 12, 31), -1250, "LIDL"),
+        (date(2027, 1, 3), 200000, "Salary October"),
+        (date(2027, 1, 4), 320, "Bakery"),
+        (date(2027, 1, 4), 320, "Bakery"),
+    ]);
+}
+
+#[test]
+fn mt940_references_duplicates_apart() {
+    let entries = mt940::read_statement(fixture("statement.sta")).unwrap();
+
+    assert_distinct_references(&entries);
+    assert_eq!(entries[0].reference, "mt940:30004/1234567:B1#1");
+}
+
+// ----- WORKSHEETS -----
+fn lines(period: &WorksheetPeriod) -> Vec<(String, &str, Option<i64>, Option<i64>)> {
+    return period.lines.iter()
+        .map(|l| (format!("{:?}", l.kind), l.label.as_str(), l.estimate, l.spent))
+        .collect();
+}
+
+#[test]
+fn worksheet_reads_periods_sections_and_separators() {
+    let periods = read_worksheet(fixture("worksheet.csv"), None).unwrap();
+
+    let dates: Vec<(NaiveDate, NaiveDate)> = periods.iter().map(|p| (p.start_date, p.end_date)).collect();
+    assert_eq!(dates, vec![
+        (date(2026, 1, 1), date(2026, 1, 31)),
+        (date(2026, 2, 1), date(2026, 2, 28)),
+    ]);
+
+    assert_eq!(lines(&periods[0]), vec![
+        ("Income".to_string(), "Salary", Some(200000), Some(200000)),
+        ("Expense(FIXED)".to_string(), "Rent", Some(80000), Some(80000)),
+        ("Expense(ESTIMATED)".to_string(), "Groceries", Some(30000), Some(31245)),
+        ("Expense(UNPLANNED)".to_string(), "Gift", None, Some(2500)),
+    ]);
+    assert_eq!(lines(&periods[1]), vec![
+        ("Expense(FIXED)".to_string(), "Rent", Some(80000), None),
+    ]);
+}
+
+#[test]
+fn worksheet_without_dates_starts_on_the_given_day() {
+    assert!(read_worksheet(fixture("undated_worksheet.csv"), None).is_err());
+
+    let periods = read_worksheet(fixture("undated_worksheet.csv"), Some(date(2026, 3, 1))).unwrap();
+
+    assert_eq!((periods[0].start_date, periods[0].end_date), (date(2026, 3, 1), date(2026, 3, 31)));
+    assert_eq!(lines(&periods[0]), vec![
+        ("Expense(ESTIMATED)".to_string(), "Rent", Some(80000), Some(80000)),
+        ("Expense(ESTIMATED)".to_string(), "Groceries", Some(30000), Some(28050)),
+    ]);
+}
+
+// ----- JOURNALS -----
+#[test]
+fn journal_reads_expense_and_income_postings() {
+    let postings = read_journal(fixture("statement.journal")).unwrap();
+
+    let read: Vec<(NaiveDate, &str, String, i64, &str)> = postings.iter()
+        .map(|p| (p.date, p.account.as_str(), format!("{:?}", p.kind), p.amount, p.description.as_str()))
+        .collect();
+
+    assert_eq!(read, vec![
+        (date(2020, 1, 5), "expenses:fixed:rent", "Expense(FIXED)".to_string(), 80000, "Rent"),
+        (date(2020, 1, 6), "expenses:food", "Expense(ESTIMATED)".to_string(), 123450, "Groceries"),
+        (date(2020, 1, 6), "expenses:unplanned:gift", "Expense(UNPLANNED)".to_string(), 2000, "Groceries"),
+        (date(2020, 1, 25), "income:salary", "Income".to_string(), 200000, "Salary"),
+        (date(2020, 2, 3), "expenses:food", "Expense(ESTIMATED)".to_string(), -1500, "Refund"),
+    ]);
+}
+
+#[test]
+fn journal_import_creates_periods_around_the_existing_ones() {
+    let ledger = Ledger::open(":memory:").unwrap();
+    let existing = ledger.create_period(date(2020, 1, 20), Some(date(2020, 1, 31)), None).unwrap();
+    let postings = read_journal(fixture("statement.journal")).unwrap();
+
+    let summary = ledger.import_journal(&postings, &LabelRules::default(), "statement.journal").unwrap();
+    assert_eq!(summary.created_periods.len(), 2);
+
+    let created: Vec<(NaiveDate, Option<NaiveDate>)> = summary.created_periods.iter()
+        .map(|id| ledger.get_period(*id).unwrap())
+        .map(|p| (p.start_date, p.end_date))
+        .collect();
+    assert_eq!(created, vec![
+        (date(2020, 1, 1), Some(date(2020, 1, 19))),
+        (date(2020, 2, 1), Some(date(2020, 2, 29))),
+    ]);
+
+    let january = summary.created_periods[0];
+    assert_eq!(ledger.get_expense(january, "rent").unwrap().estimate, 80000);
+    assert_eq!(ledger.get_expense(january, "food").unwrap().spent, 123450);
+    assert_eq!(ledger.get_income(existing, "salary").unwrap().received, 200000);
+
+    let again = ledger.import_journal(&postings, &LabelRules::default(), "statement.journal").unwrap();
+    assert_eq!(again.duplicates, 5);
+    assert!(again.created_periods.is_empty());
+}
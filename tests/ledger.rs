@@ -0,0 +1,117 @@
+#![allow(clippy::needless_return)]
+
+use chrono::{Local, NaiveDate};
+use ebenezer::{Error, Ledger};
+use ebenezer::ledger::get_balance;
+use ebenezer::schedule::Schedule;
+use ebenezer::structs::ExpenseType;
+
+fn open() -> Ledger {
+    return Ledger::open(":memory:").unwrap();
+}
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    return NaiveDate::from_ymd_opt(y, m, d).unwrap();
+}
+
+#[test]
+fn correcting_a_received_income_corrects_the_balance() {
+    let ledger = open();
+    let period = ledger.get_current_period().unwrap();
+
+    ledger.create_income(period, "salary", 250000, "EUR", false).unwrap();
+    ledger.override_income(&ledger.get_income(period, "salary").unwrap(), 200000).unwrap();
+
+    let income = ledger.get_income(period, "salary").unwrap();
+    assert_eq!(income.value, 200000);
+    assert_eq!(income.received, 200000);
+    assert_eq!(get_balance(&ledger.get_incomes(period).unwrap(), &[]), 200000);
+}
+
+#[test]
+fn correcting_a_partly_received_income_caps_what_was_received() {
+    let ledger = open();
+    let period = ledger.get_current_period().unwrap();
+
+    ledger.expect_income(period, "invoice", 100000, "EUR", None, false).unwrap();
+    ledger.receive_income(&ledger.get_income(period, "invoice").unwrap(), 60000).unwrap();
+
+    ledger.override_income(&ledger.get_income(period, "invoice").unwrap(), 120000).unwrap();
+    assert_eq!(ledger.get_income(period, "invoice").unwrap().received, 60000);
+
+    ledger.override_income(&ledger.get_income(period, "invoice").unwrap(), 50000).unwrap();
+    assert_eq!(ledger.get_income(period, "invoice").unwrap().received, 50000);
+}
+
+#[test]
+fn catching_up_rolls_over_on_every_missed_boundary() {
+    let ledger = open();
+    let first = ledger.get_current_period().unwrap();
+
+    ledger.create_expense(first, "rent", ExpenseType::FIXED, 50000, 0, "EUR").unwrap();
+    ledger.set_period_dates(&ledger.get_period(first).unwrap(), date(2026, 1, 31), None).unwrap();
+
+    let created = ledger.catch_up(&Schedule::Monthly { day: 31 }, date(2026, 4, 15)).unwrap();
+    assert_eq!(created.len(), 2);
+
+    let periods: Vec<(NaiveDate, Option<NaiveDate>)> = ledger.get_periods().unwrap()
+        .into_iter()
+        .map(|p| (p.start_date, p.end_date))
+        .collect();
+
+    assert_eq!(periods, vec![
+        (date(2026, 1, 31), Some(date(2026, 2, 27))),
+        (date(2026, 2, 28), Some(date(2026, 3, 30))),
+        (date(2026, 3, 31), None),
+    ]);
+
+    for id in created {
+        assert_eq!(ledger.get_expense(id, "rent").unwrap().estimate, 50000);
+    }
+
+    assert!(ledger.catch_up(&Schedule::Monthly { day: 31 }, date(2026, 4, 15)).unwrap().is_empty());
+}
+
+#[test]
+fn catching_up_skips_an_undone_roll_over() {
+    let ledger = open();
+    let first = ledger.get_current_period().unwrap();
+
+    ledger.set_period_dates(&ledger.get_period(first).unwrap(), date(2026, 1, 1), None).unwrap();
+    ledger.catch_up(&Schedule::Monthly { day: 1 }, date(2026, 2, 10)).unwrap();
+    ledger.undo(1).unwrap();
+
+    assert!(ledger.catch_up(&Schedule::Monthly { day: 1 }, date(2026, 2, 10)).unwrap().is_empty());
+    assert_eq!(ledger.get_current_period().unwrap(), first);
+}
+
+#[test]
+fn deleting_a_period_is_logged_under_the_current_one() {
+    let ledger = open();
+    let current = ledger.get_current_period().unwrap();
+    let past = ledger.create_period(date(2020, 1, 1), Some(date(2020, 1, 31)), None).unwrap();
+
+    ledger.delete_period(&ledger.get_period(past).unwrap()).unwrap();
+
+    let logs = ledger.get_period_logs(current).unwrap();
+    assert_eq!(logs[0].action, "DELETE_PERIOD");
+    assert_eq!(logs[0].period_id, current);
+}
+
+#[test]
+fn rolling_a_period_started_today_is_refused() {
+    let ledger = open();
+    let current = ledger.get_current_period().unwrap();
+    let today = Local::now().date_naive();
+
+    ledger.set_period_dates(&ledger.get_period(current).unwrap(), today, None).unwrap();
+    assert!(matches!(ledger.roll(), Err(Error::PeriodStartedToday(id)) if id == current));
+    assert_eq!(ledger.get_current_period().unwrap(), current);
+
+    let yesterday = today.pred_opt().unwrap();
+    ledger.set_period_dates(&ledger.get_period(current).unwrap(), yesterday, None).unwrap();
+
+    let new_period = ledger.roll().unwrap();
+    assert_eq!(ledger.get_period(current).unwrap().end_date, Some(yesterday));
+    assert_eq!(ledger.get_period(new_period).unwrap().start_date, today);
+}
@@ -0,0 +1,28 @@
+#![allow(clippy::needless_return)]
+
+use ebenezer::Ledger;
+use ebenezer::ledger::get_balance;
+use ebenezer::settings::REPORTING_CURRENCY;
+
+// The reporting currency is read once per process, so this test lives in its own file.
+#[test]
+fn lines_in_a_reporting_currency_other_than_the_euro_need_no_rate() {
+    std::env::set_var("EBENEZER_CURRENCY", "$");
+    assert_eq!(*REPORTING_CURRENCY, "USD");
+
+    let ledger = Ledger::open(":memory:").unwrap();
+    let id = ledger.get_current_period().unwrap();
+    let period = ledger.get_period(id).unwrap();
+
+    ledger.create_income(id, "salary", 100000, "USD", false).unwrap();
+    ledger.spend(id, "x", 5000, "USD", None, None).unwrap();
+    ledger.spend(id, "trip", 2000, "EUR", None, None).unwrap();
+
+    let incomes = ledger.get_incomes(id).unwrap();
+    let expenses = ledger.get_expenses(id).unwrap();
+    let (incomes, expenses, missing) = ledger.in_reporting_currency(&period, &incomes, &expenses).unwrap();
+
+    assert_eq!(get_balance(&incomes, &expenses), 95000);
+    assert_eq!(expenses.len(), 1);
+    assert_eq!(missing, vec!["EUR".to_string()]);
+}